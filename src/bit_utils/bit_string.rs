@@ -49,7 +49,7 @@ impl BitString {
     {
         let b = bit.into();
         for _ in 0..times.into() {
-            self.push_bit(b.clone());
+            self.push_bit(b);
         }
     }
 
@@ -86,6 +86,10 @@ impl BitString {
         self.bits_len
     }
 
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
     #[allow(unused)]
     pub fn as_hex(&self) -> HexStr {
         let mut chars: HexStr = HexStr(Vec::new());
@@ -96,11 +100,8 @@ impl BitString {
             let mut val: u8 = 0;
             for _ in 0..4 {
                 val <<= 1;
-                if let Ok(bit) = self.get_bit(index) {
-                    match bit {
-                        Bit::One => val |= 1,
-                        _ => {}
-                    }
+                if let Ok(Bit::One) = self.get_bit(index) {
+                    val |= 1;
                 }
                 index += 1;
             }
@@ -180,6 +181,7 @@ mod test {
     use super::*;
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn bit_basics() {
         let mut bit_string: BitString = BitString::new();
 
@@ -1,43 +1,77 @@
-/// A bitmap representation for storing and manipulating bit-level data
-///
-/// # Structure
-///
-/// The `BitMap` stores a 2D grid of bits as a vector of bytes, optimized for space efficiency
-///
-/// # Methods
-///
-/// Provides methods to:
-/// - Create a new bitmap
-/// - Set and get individual bits
-/// - Invert bits
-/// - Get bitmap size
-/// - Save bitmap to a file
-///
-/// # Example
-///
-/// ```rust
-/// let mut bitmap = BitMap::new(10);
-/// bitmap.set(5, 7, 1);
-/// assert_eq!(bitmap.get(5, 7), Bit::One);
-/// ```
-
+//! A bitmap representation for storing and manipulating bit-level data
+//!
+//! # Structure
+//!
+//! The `BitMap` stores a 2D grid of bits as a vector of bytes, optimized for space efficiency
+//!
+//! # Methods
+//!
+//! Provides methods to:
+//! - Create a new bitmap
+//! - Set and get individual bits
+//! - Invert bits
+//! - Record the role of every module
+//! - Get bitmap size
+//! - Save bitmap to a BMP file
+//!
+//! # Example
+//!
+//! ```rust
+//! let mut bitmap = BitMap::new(10);
+//! bitmap.set(5, 7, 1);
+//! assert_eq!(bitmap.get(5, 7), Bit::One);
+//! ```
 
 use super::bit::Bit;
+use crate::render::{bmp::BmpOptions, RenderOptions};
 use std::fmt::Display;
-use std::fs::File;
-use std::io::Write;
+use std::io::{self, Write};
 
 // Helper functions
 fn get_byte_location(j: usize) -> (usize, usize) {
     (j / 8, j % 8)
 }
 
+/// Function of a module in a QR code symbol
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ModuleRole {
+    /// Data and error correction codewords and the remainder bits
+    Data,
+    /// Outer dark ring of a finder pattern and the light ring inside it
+    FinderOuter,
+    /// Dark 3 x 3 centre of a finder pattern
+    FinderInner,
+    /// Light border between a finder pattern and the rest of the symbol
+    Separator,
+    /// Alternating modules between the finder patterns
+    Timing,
+    /// 5 x 5 alignment pattern
+    Alignment,
+    /// Error correction level and mask pattern
+    FormatInformation,
+    /// Version of large symbols
+    VersionInformation,
+    /// Single dark module next to the bottom left finder pattern
+    DarkModule,
+    /// Data module cleared to make room for a logo
+    Logo,
+}
+
+impl ModuleRole {
+    /// Returns `true` for the modules of function patterns, which must be drawn unchanged
+    pub fn is_function_pattern(&self) -> bool {
+        !matches!(self, ModuleRole::Data | ModuleRole::Logo)
+    }
+}
+
 // bitmaps can only be square in size
 pub struct BitMap {
     /// Internal storage of bits using byte arrays
     map: Vec<Vec<u8>>,
     /// Size of the bitmap (width and height)
     size: usize,
+    /// Role of every module row by row, empty until a role is set
+    roles: Vec<ModuleRole>,
 }
 
 impl BitMap {
@@ -54,6 +88,7 @@ impl BitMap {
         Self {
             map: vec![vec![0u8; (size / 8) + 1]; size],
             size,
+            roles: Vec::new(),
         }
     }
 
@@ -114,6 +149,47 @@ impl BitMap {
         (row[byte] & (1 << byte_offset)).into()
     }
 
+    /// Sets the role of a module
+    ///
+    /// # Arguments
+    ///
+    /// * `i` - Row index
+    /// * `j` - Column index
+    /// * `role` - Function of the module in the symbol
+    pub(crate) fn set_role(&mut self, i: usize, j: usize, role: ModuleRole) {
+        if i >= self.size || j >= self.size {
+            return;
+        }
+
+        if self.roles.is_empty() {
+            self.roles = vec![ModuleRole::Data; self.size * self.size];
+        }
+
+        self.roles[i * self.size + j] = role;
+    }
+
+    /// Retrieves the role of a module
+    ///
+    /// # Arguments
+    ///
+    /// * `i` - Row index
+    /// * `j` - Column index
+    ///
+    /// # Returns
+    ///
+    /// The role recorded when the QR code was created, `ModuleRole::Data` for bitmaps without
+    /// roles and for locations outside the bitmap
+    pub fn role(&self, i: usize, j: usize) -> ModuleRole {
+        if i >= self.size || j >= self.size {
+            return ModuleRole::Data;
+        }
+
+        self.roles
+            .get(i * self.size + j)
+            .copied()
+            .unwrap_or(ModuleRole::Data)
+    }
+
     /// Returns the size of the bitmap
     ///
     /// # Returns
@@ -123,7 +199,7 @@ impl BitMap {
         self.size
     }
 
-    /// Saves the bitmap to a file in BMP format
+    /// Saves the bitmap to a file in BMP format with the default options
     ///
     /// # Arguments
     ///
@@ -131,131 +207,38 @@ impl BitMap {
     ///
     /// # Remarks
     ///
-    /// Creates a 1bpp bitmap image with a black and white color palette
-    pub fn save_to_file<P>(&self, path: P)
+    /// Creates a 1bpp black and white bitmap image. Use `save_bmp` to pick the pixel format,
+    /// resolution and colours, or `write_bmp` to write to any `io::Write`
+    pub fn save_to_file<P>(&self, path: P) -> io::Result<()>
     where
         P: AsRef<std::path::Path>,
     {
-        if let Ok(mut file) = File::create(path) {
-            // Write the bmp header 14 bytes
-
-            // header field
-            _ = file.write(&[0x42, 0x4D]); // ASCII BM
-
-            // Size of the bmp file in bytes
-            let file_size = (62 + (self.size() * self.size())) as u32;
-            // let file_size = (62 + 100) as u32;
-            _ = file.write(&[
-                file_size as u8,
-                (file_size >> 8) as u8,
-                (file_size >> 16) as u8,
-                (file_size >> 24) as u8,
-            ]);
-
-            // reserved bytes
-            _ = file.write(&[0, 0, 0, 0]);
-
-            // Offset of the pixel array
-            _ = file.write(&[62, 0, 0, 0]);
-
-            // BITMAPINFOHEADER --------
-            // Size of this header (40 bytes)
-            _ = file.write(&[40, 0, 0, 0]);
-
-            // Bitmap width in pixels
-            // _ = file.write(&[10, 0, 0, 0]);
-            // _ = file.write(&[10, 0, 0, 0]);
-            _ = file.write(&[
-                self.size() as u8,
-                (self.size() >> 8) as u8,
-                (self.size() >> 16) as u8,
-                (self.size() >> 24) as u8,
-            ]);
-
-            // Bitmap height in pixels
-            _ = file.write(&[
-                self.size() as u8,
-                (self.size() >> 8) as u8,
-                (self.size() >> 16) as u8,
-                (self.size() >> 24) as u8,
-            ]);
-
-            // Number of color planes (must be 1)
-            _ = file.write(&[1, 0]);
-
-            // Number of bits per pixel (1 in our case)
-            _ = file.write(&[1, 0]);
-
-            // Compression method being used (no compression)
-            _ = file.write(&[0, 0, 0, 0]);
-
-            // Image size (can be ignored)
-            _ = file.write(&[0, 0, 0, 0]);
-
-            // Horizontal resolution of the bitmap
-            _ = file.write(&[255, 255, 255, 255]);
-
-            // Vertical resolution of the bitmap
-            _ = file.write(&[255, 255, 255, 255]);
-
-            // Number of colors in the palette
-            _ = file.write(&[2, 0, 0, 0]);
-
-            // Number of important colors in the palette
-            _ = file.write(&[0, 0, 0, 0]);
-
-            // Color Palette
-            // White
-            _ = file.write(&[255, 255, 255, 0]);
-            // Black
-            _ = file.write(&[0, 0, 0, 0]);
-
-            // Write the bits to the bitmap
-            let mut bit_index = 0;
-            let mut current_byte = 0;
-            for i in (0..self.size()).rev() {
-                for j in 0..self.size() {
-                    match self.get(i, j) {
-                        Bit::Zero => {}
-                        Bit::One => {
-                            current_byte |= 1 << (31 - bit_index);
-                        }
-                    }
-
-                    bit_index += 1;
-
-                    if bit_index == 32 {
-                        _ = file.write(&[
-                            (current_byte >> 24) as u8,
-                            (current_byte >> 16) as u8,
-                            (current_byte >> 8) as u8,
-                            current_byte as u8,
-                        ]);
-                        current_byte = 0;
-                        bit_index = 0;
-                    }
-                }
+        self.save_bmp(path, &BmpOptions::default(), &RenderOptions::default())
+    }
 
-                if bit_index != 0 {
-                    _ = file.write(&[
-                        (current_byte >> 24) as u8,
-                        (current_byte >> 16) as u8,
-                        (current_byte >> 8) as u8,
-                        current_byte as u8,
-                    ]);
-                }
-                current_byte = 0;
-                bit_index = 0;
-            }
-        }
+    /// Writes the bitmap as a BMP image with the default options
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    ///
+    /// # Remarks
+    ///
+    /// Writes the same 1bpp black and white image as `save_to_file`, use `write_bmp` to pick the
+    /// pixel format, resolution and colours
+    pub fn write_bmp_default<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_bmp(writer, &BmpOptions::default(), &RenderOptions::default())
     }
 }
 
 /// Implements a text-based display of the bitmap
 ///
 /// Renders the bitmap using block characters, where:
-/// - `██` represents a black/set bit
-/// - `  ` represents a white/unset bit
+/// - `██` represents a light/unset bit
+/// - `  ` represents a dark/set bit
+///
+/// The blocks are drawn in the foreground colour, so this only scans on terminals with light text
+/// on a dark background. `render_ansi` paints explicit colours for any terminal theme.
 impl Display for BitMap {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         for _ in 0..=self.size + 2 {
@@ -310,6 +293,17 @@ mod test {
         assert_eq!(bit_map.get(5, 7), Bit::One);
     }
 
+    #[test]
+    fn test_module_roles() {
+        let mut bit_map = BitMap::new(10);
+
+        assert_eq!(bit_map.role(5, 7), ModuleRole::Data);
+        bit_map.set_role(0, 1, ModuleRole::Timing);
+        assert_eq!(bit_map.role(0, 1), ModuleRole::Timing);
+        assert!(bit_map.role(0, 1).is_function_pattern());
+        assert!(!bit_map.role(5, 7).is_function_pattern());
+    }
+
     #[test]
     fn test_bitmap_sizing() {
         let bit_map = BitMap::new(10);
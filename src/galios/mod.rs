@@ -73,28 +73,22 @@ impl Polynomial {
     }
 
     pub fn convert_to_exponent_notation(&mut self) {
-        match self.notation {
-            Notation::Integer => {
-                for elem in self.data.get_mut() {
-                    *elem = get_antilog(*elem);
-                }
-
-                self.notation = Notation::Exponent;
+        if let Notation::Integer = self.notation {
+            for elem in self.data.get_mut() {
+                *elem = get_antilog(*elem);
             }
-            _ => {}
+
+            self.notation = Notation::Exponent;
         }
     }
 
     pub fn convert_to_integer_notation(&mut self) {
-        match self.notation {
-            Notation::Exponent => {
-                for elem in self.data.get_mut() {
-                    *elem = get_log(*elem);
-                }
-
-                self.notation = Notation::Integer;
+        if let Notation::Exponent = self.notation {
+            for elem in self.data.get_mut() {
+                *elem = get_log(*elem);
             }
-            _ => {}
+
+            self.notation = Notation::Integer;
         }
     }
 
@@ -7,13 +7,31 @@
 //! - `bit_utils`: Utility functions for bit manipulation.
 //! - `galios`: Functions for Galois field arithmetic.
 //! - `qr_code`: Core QR code generation logic.
+//! - `render`: Image writers for finished QR codes.
+//!
+//! ## Pipeline
+//!
+//! The generation stages can be run one at a time through the type-state pipeline, starting
+//! from `Analyzed`. Each stage returns the next one (`Encoded`, `Protected`, `Interleaved` and
+//! `Placed`) so they can only be called in order, and every stage can be inspected.
+//!
+//! ```rust
+//! use DEWQ::*;
+//!
+//! let encoded = Analyzed::new("HELLO, WORLD!", ErrorCorrectionLevel::Q).encode();
+//! println!("{:?}", encoded.codewords());
+//!
+//! let qr_code = encoded.protect().interleave().place().into_bit_map();
+//! ```
 //!
 //! ## Functions
 //!
 //! ### `create_qr_code`
 //!
 //! ```rust
+//! # use DEWQ::*;
 //! pub fn create_qr_code(data: &str, error_correction_level: ErrorCorrectionLevel) -> BitMap
+//! # { create_qr_code(data, error_correction_level) }
 //! ```
 //!
 //! Creates a QR code from the given data and error correction level.
@@ -27,10 +45,38 @@
 //!
 //! A `BitMap` representing the generated QR code.
 //!
+//! Text that is not ASCII is transcoded to the most compact character set and marked with its
+//! ECI designator.
+//!
+//! ### `create_eci_qr_code`
+//!
+//! Creates a QR code with the data transcoded to a specific character set, such as ISO-8859-2 or
+//! Shift JIS, for scanners that expect it.
+//!
+//! ### `create_gs1_qr_code`
+//!
+//! Creates a GS1 QR code from Application Identifier/value pairs, validating every value and
+//! inserting the group separators automatically.
+//!
+//! ### `create_fnc1_qr_code`
+//!
+//! Creates a QR code whose data is marked with an FNC1 mode indicator, such as an industry
+//! format identified by an application indicator.
+//!
+//! ### `create_halftone_qr_code`
+//!
+//! Creates a QR code with a grayscale picture dithered into 3 x 3 sub-modules, with the pad
+//! codewords and data mask chosen to follow the picture.
+//!
+//! ### `create_logo_qr_code`
+//!
+//! Creates a QR code with a centred area cleared for a logo, raising the error correction level
+//! or version until the logo only damages a safe share of the correctable codewords.
+//!
 //! ## Examples
 //!
 //! ```rust
-//! use qr_code::*;
+//! use DEWQ::*;
 //!
 //! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
 //! println!("{}", qr_code);
@@ -38,13 +84,32 @@
 
 #![allow(non_snake_case)]
 
-pub use bit_utils::bitmap::BitMap;
-pub use qr_code::ErrorCorrectionLevel;
-use qr_code::QRMode;
+pub use bit_utils::bitmap::{BitMap, ModuleRole};
+pub use qr_code::pipeline::{Analyzed, Encoded, EncodingMode, Interleaved, Placed, Protected};
+pub use qr_code::{
+    gb2312_decode, Charset, ErrorCorrectionLevel, Fnc1, Gs1Error, LogoError, LogoOptions, LogoShape,
+};
+pub use render::ansi::{AnsiColor, AnsiOptions};
+pub use render::bmp::{BmpError, BmpFormat, BmpOptions};
+pub use render::caption::{Caption, CaptionAlign};
+pub use render::eps::{EpsColor, EpsOptions};
+pub use render::halftone::HALFTONE_SCALE;
+pub use render::logo::LogoImage;
+pub use render::netpbm::{GrayImage, NetpbmError, NetpbmFormat};
+pub use render::paint::{ContrastWarning, Paint, Region, RegionColors, MIN_CONTRAST_RATIO};
+pub use render::pdf::{PageSize, PdfDocument, PdfOptions};
+pub use render::raster::Scanlines;
+pub use render::sizing::{Length, PhysicalSize, Rounding, SizingWarning};
+pub use render::style::{EyeShape, ModuleShape, ModuleStyle};
+pub use render::svg::SvgOptions;
+pub use render::terminal::TerminalProtocol;
+pub use render::text::{Polarity, TextMode, TextOptions};
+pub use render::{Color, RenderOptions, Renderer};
 
 mod bit_utils;
 mod galios;
 mod qr_code;
+mod render;
 
 /// Creates a QR code bitmap from input data with specified error correction
 ///
@@ -71,7 +136,7 @@ mod qr_code;
 /// # Examples
 ///
 /// ```
-/// use qr_code::{create_qr_code, ErrorCorrectionLevel};
+/// use DEWQ::{create_qr_code, ErrorCorrectionLevel};
 ///
 /// let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
 /// ```
@@ -80,12 +145,264 @@ mod qr_code;
 ///
 /// The function's complexity depends on the data length and chosen error correction level
 pub fn create_qr_code(data: &str, error_correction_level: ErrorCorrectionLevel) -> BitMap {
-    let mut qr_code = QRMode::analyze_data(data, error_correction_level);
-    let mut bits = qr_code.encode();
-    let qr_data = qr_code.generate_error_correction(bits);
-    bits = qr_code.structure_codewords(qr_data);
+    Analyzed::new(data, error_correction_level)
+        .encode()
+        .protect()
+        .interleave()
+        .place()
+        .into_bit_map()
+}
+
+/// Creates a QR code bitmap with the data transcoded to a specific character set
+///
+/// The data is placed in byte mode behind the ECI designator of the character set
+///
+/// # Arguments
+///
+/// * `data` - The string data to be encoded in the QR code
+/// * `charset` - The character set the scanner expects
+/// * `error_correction_level` - The error correction level for redundancy and recovery
+///
+/// # Returns
+///
+/// A `BitMap` representing the fully generated QR code, or `None` if the data cannot be
+/// represented in the character set
+///
+/// # Errors
+///
+/// Panics if the data exceeds QR code capacity
+///
+/// # Examples
+///
+/// ```
+/// use DEWQ::{create_eci_qr_code, Charset, ErrorCorrectionLevel};
+///
+/// let qr_code = create_eci_qr_code("Zażółć", Charset::Iso8859_2, ErrorCorrectionLevel::M);
+/// assert!(qr_code.is_some());
+/// ```
+pub fn create_eci_qr_code(
+    data: &str,
+    charset: Charset,
+    error_correction_level: ErrorCorrectionLevel,
+) -> Option<BitMap> {
+    let analyzed = Analyzed::with_charset(data, charset, error_correction_level)?;
+
+    Some(
+        analyzed
+            .encode()
+            .protect()
+            .interleave()
+            .place()
+            .into_bit_map(),
+    )
+}
+
+/// Creates a GS1 QR code bitmap from Application Identifier/value pairs
+///
+/// The values are validated against their Application Identifier (length, character set, check
+/// digit and dates), joined into a GS1 element string with group separators where needed and
+/// encoded behind the FNC1 first position mode indicator
+///
+/// # Arguments
+///
+/// * `elements` - Application Identifier/value pairs such as `("01", "09506000134352")`
+/// * `error_correction_level` - The error correction level for redundancy and recovery
+///
+/// # Returns
+///
+/// A `BitMap` representing the fully generated QR code
+///
+/// # Errors
+///
+/// Returns a `Gs1Error` if any of the values is invalid or the data exceeds QR code capacity
+///
+/// # Examples
+///
+/// ```
+/// use DEWQ::{create_gs1_qr_code, ErrorCorrectionLevel};
+///
+/// let qr_code = create_gs1_qr_code(
+///     &[("01", "09506000134352"), ("17", "201225"), ("10", "ABC123"), ("21", "12345")],
+///     ErrorCorrectionLevel::M,
+/// )
+/// .unwrap();
+/// ```
+pub fn create_gs1_qr_code(
+    elements: &[(&str, &str)],
+    error_correction_level: ErrorCorrectionLevel,
+) -> Result<BitMap, Gs1Error> {
+    let analyzed = Analyzed::gs1(elements, error_correction_level)?;
+
+    Ok(analyzed
+        .encode()
+        .protect()
+        .interleave()
+        .place()
+        .into_bit_map())
+}
+
+/// Creates a QR code bitmap with the data marked by an FNC1 mode indicator
+///
+/// Group separators (GS, 0x1D) in the data are encoded as FNC1 field separators
+///
+/// # Arguments
+///
+/// * `data` - The string data to be encoded in the QR code
+/// * `fnc1` - The FNC1 mode indicator, e.g. `Fnc1::application_indicator("37")`
+/// * `error_correction_level` - The error correction level for redundancy and recovery
+///
+/// # Returns
+///
+/// A `BitMap` representing the fully generated QR code
+///
+/// # Errors
+///
+/// Panics if the data cannot be encoded or exceeds QR code capacity
+///
+/// # Examples
+///
+/// ```
+/// use DEWQ::{create_fnc1_qr_code, ErrorCorrectionLevel, Fnc1};
+///
+/// let fnc1 = Fnc1::application_indicator("37").unwrap();
+/// let qr_code = create_fnc1_qr_code("AB1234", fnc1, ErrorCorrectionLevel::M);
+/// ```
+pub fn create_fnc1_qr_code(
+    data: &str,
+    fnc1: Fnc1,
+    error_correction_level: ErrorCorrectionLevel,
+) -> BitMap {
+    Analyzed::with_fnc1(data, fnc1, error_correction_level)
+        .encode()
+        .protect()
+        .interleave()
+        .place()
+        .into_bit_map()
+}
+
+/// Creates a halftone QR code bitmap with a grayscale picture blended into the modules
+///
+/// The pad codewords after the data are set to the modules of the picture and the data mask
+/// that matches the picture best is used. Every module is then drawn as 3 x 3 sub-modules with
+/// the module value in the centre and the dithered picture around it
+///
+/// # Arguments
+///
+/// * `data` - The string data to be encoded in the QR code
+/// * `error_correction_level` - The error correction level for redundancy and recovery
+/// * `picture` - Grayscale picture stretched over the symbol
+///
+/// # Returns
+///
+/// A `BitMap` of sub-modules, `HALFTONE_SCALE` times the size of the symbol
+///
+/// # Errors
+///
+/// Panics if the data cannot be encoded or exceeds QR code capacity
+///
+/// # Examples
+///
+/// ```
+/// use DEWQ::{create_halftone_qr_code, ErrorCorrectionLevel, GrayImage, HALFTONE_SCALE};
+///
+/// let picture = GrayImage { width: 2, height: 2, pixels: vec![0, 255, 255, 0] };
+/// let qr_code = create_halftone_qr_code("HELLO", ErrorCorrectionLevel::H, &picture);
+/// assert_eq!(qr_code.size(), 21 * HALFTONE_SCALE);
+/// ```
+pub fn create_halftone_qr_code(
+    data: &str,
+    error_correction_level: ErrorCorrectionLevel,
+    picture: &GrayImage,
+) -> BitMap {
+    qr_code::create_picture_bit_map(data, error_correction_level, picture).halftone(picture)
+}
+
+/// Creates a QR code bitmap with the centre cleared for a logo
+///
+/// The codewords under the logo are counted for every error correction block. When a block
+/// would lose more than the allowed share of the codewords it can correct, the error correction
+/// level and then the version are raised as far as the options permit
+///
+/// # Arguments
+///
+/// * `data` - The string data to be encoded in the QR code
+/// * `error_correction_level` - The lowest error correction level to use
+/// * `options` - Area of the logo, allowed damage and whether to raise the level or version
+///
+/// # Returns
+///
+/// A `BitMap` with the data modules under the logo cleared and given the `ModuleRole::Logo`
+/// role
+///
+/// # Errors
+///
+/// Returns a `LogoError` if the logo covers a function pattern or damages too many codewords,
+/// panics if the data exceeds QR code capacity
+///
+/// # Examples
+///
+/// ```
+/// use DEWQ::{create_logo_qr_code, ErrorCorrectionLevel, LogoOptions, LogoShape};
+///
+/// let options = LogoOptions {
+///     shape: LogoShape::Circle { diameter: 7 },
+///     ..LogoOptions::default()
+/// };
+/// let qr_code = create_logo_qr_code("https://example.com", ErrorCorrectionLevel::L, &options);
+/// assert!(qr_code.is_ok());
+/// ```
+pub fn create_logo_qr_code(
+    data: &str,
+    error_correction_level: ErrorCorrectionLevel,
+    options: &LogoOptions,
+) -> Result<BitMap, LogoError> {
+    use ErrorCorrectionLevel::*;
+
+    let place = |analyzed: Analyzed| {
+        analyzed
+            .encode()
+            .protect()
+            .interleave()
+            .place()
+            .with_logo(options)
+    };
+
+    let levels = [L, M, Q, H];
+    let first = levels
+        .iter()
+        .position(|level| *level == error_correction_level)
+        .unwrap_or(0);
+    let last = if options.raise_error_correction {
+        levels.len() - 1
+    } else {
+        first
+    };
+
+    let mut level = error_correction_level;
+    let mut placed = place(Analyzed::new(data, level));
+
+    for next in &levels[first + 1..=last] {
+        if placed.is_ok() {
+            break;
+        }
+
+        level = *next;
+        placed = place(Analyzed::new(data, level));
+    }
+
+    if options.raise_version {
+        let mut version = Analyzed::new(data, level).version();
+
+        while placed.is_err() && version < 40 {
+            version += 1;
+
+            if let Some(analyzed) = Analyzed::new(data, level).with_version(version) {
+                placed = place(analyzed);
+            }
+        }
+    }
 
-    qr_code.create_bit_map(bits)
+    placed.map(Placed::into_bit_map)
 }
 
 #[cfg(test)]
@@ -1,18 +1,20 @@
-use qr_code::{ErrorCorrectionLevel, QRMode};
 use std::env;
-
-mod bit_utils;
-mod galios;
-mod qr_code;
+use std::process;
+use DEWQ::{create_qr_code, ErrorCorrectionLevel, TerminalProtocol};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if args.len() < 3 {
+        eprintln!("Usage: {} <data> <L|M|Q|H> [save path]", args[0]);
+        eprintln!("Prints the QR code to the terminal when no save path is given");
+        process::exit(1);
+    }
+
     let data = &args[1];
     let error_correction_level = &args[2];
-    let save_path = &args[3];
 
-    let mut qr_code = QRMode::analyze_data(
+    let bitmap = create_qr_code(
         data.as_str(),
         match error_correction_level.as_str() {
             "L" | "l" => ErrorCorrectionLevel::L,
@@ -22,10 +24,14 @@ fn main() {
             _ => panic!(),
         },
     );
-    let mut bits = qr_code.encode();
-    let qr_data = qr_code.generate_error_correction(bits);
-    bits = qr_code.structure_codewords(qr_data);
 
-    let bitmap = qr_code.create_bit_map(bits);
-    bitmap.save_to_file(save_path.as_str());
+    match args.get(3) {
+        Some(save_path) => {
+            if let Err(error) = bitmap.save_to_file(save_path.as_str()) {
+                eprintln!("Could not save {}: {}", save_path, error);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", bitmap.to_terminal(TerminalProtocol::detect())),
+    }
 }
@@ -0,0 +1,337 @@
+//! # Extended Channel Interpretation
+//!
+//! Transcodes text to legacy character sets so it can be placed in byte mode behind the ECI
+//! designator of the character set. Scanners that expect ISO-8859-x, Windows-125x, Shift JIS or
+//! Big5 content then read the data without any guessing.
+//!
+//! ISO-8859-12 was never published, so it has no designator and is not supported.
+//!
+//! ```rust,ignore
+//! let (charset, bytes) = Charset::most_compact("Grüße");
+//! assert_eq!(charset, Charset::Iso8859_1);
+//! assert_eq!(charset.decode(&bytes), "Grüße");
+//! ```
+
+use crate::qr_code::charsets::*;
+
+/// Character sets that can be designated with an ECI
+///
+/// The variants are ordered by preference, when several character sets encode the data in the
+/// same number of bytes the first one is picked
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Charset {
+    Iso8859_1,
+    Iso8859_2,
+    Iso8859_3,
+    Iso8859_4,
+    Iso8859_5,
+    Iso8859_6,
+    Iso8859_7,
+    Iso8859_8,
+    Iso8859_9,
+    Iso8859_10,
+    Iso8859_11,
+    Iso8859_13,
+    Iso8859_14,
+    Iso8859_15,
+    Iso8859_16,
+    Windows1250,
+    Windows1251,
+    Windows1252,
+    ShiftJis,
+    Big5,
+    Utf8,
+}
+
+impl Charset {
+    /// Every supported character set in order of preference
+    pub const ALL: [Charset; 21] = [
+        Charset::Iso8859_1,
+        Charset::Iso8859_2,
+        Charset::Iso8859_3,
+        Charset::Iso8859_4,
+        Charset::Iso8859_5,
+        Charset::Iso8859_6,
+        Charset::Iso8859_7,
+        Charset::Iso8859_8,
+        Charset::Iso8859_9,
+        Charset::Iso8859_10,
+        Charset::Iso8859_11,
+        Charset::Iso8859_13,
+        Charset::Iso8859_14,
+        Charset::Iso8859_15,
+        Charset::Iso8859_16,
+        Charset::Windows1250,
+        Charset::Windows1251,
+        Charset::Windows1252,
+        Charset::ShiftJis,
+        Charset::Big5,
+        Charset::Utf8,
+    ];
+
+    /// Returns the ECI designator of the character set
+    pub fn designator(&self) -> u32 {
+        match self {
+            Charset::Iso8859_1 => 3,
+            Charset::Iso8859_2 => 4,
+            Charset::Iso8859_3 => 5,
+            Charset::Iso8859_4 => 6,
+            Charset::Iso8859_5 => 7,
+            Charset::Iso8859_6 => 8,
+            Charset::Iso8859_7 => 9,
+            Charset::Iso8859_8 => 10,
+            Charset::Iso8859_9 => 11,
+            Charset::Iso8859_10 => 12,
+            Charset::Iso8859_11 => 13,
+            Charset::Iso8859_13 => 15,
+            Charset::Iso8859_14 => 16,
+            Charset::Iso8859_15 => 17,
+            Charset::Iso8859_16 => 18,
+            Charset::ShiftJis => 20,
+            Charset::Windows1250 => 21,
+            Charset::Windows1251 => 22,
+            Charset::Windows1252 => 23,
+            Charset::Utf8 => 26,
+            Charset::Big5 => 28,
+        }
+    }
+
+    /// Looks up the character set of an ECI designator
+    ///
+    /// # Arguments
+    ///
+    /// * `designator` - The ECI designator read from the data
+    ///
+    /// # Returns
+    ///
+    /// The matching `Charset`, or `None` if the designator is not supported
+    pub fn from_designator(designator: u32) -> Option<Charset> {
+        // Designator 1 is the obsolete ISO-8859-1 designator from the 2000 version of the standard
+        if designator == 1 {
+            return Some(Charset::Iso8859_1);
+        }
+
+        Charset::ALL
+            .into_iter()
+            .find(|charset| charset.designator() == designator)
+    }
+
+    /// Number of bits the ECI mode indicator and designator take up
+    pub(crate) fn bit_length(&self) -> usize {
+        match self.designator() {
+            0..=127 => 4 + 8,
+            128..=16383 => 4 + 16,
+            _ => 4 + 24,
+        }
+    }
+
+    fn single_byte_table(&self) -> Option<&'static [u16; 128]> {
+        match self {
+            Charset::Iso8859_1 => Some(&ISO_8859_1),
+            Charset::Iso8859_2 => Some(&ISO_8859_2),
+            Charset::Iso8859_3 => Some(&ISO_8859_3),
+            Charset::Iso8859_4 => Some(&ISO_8859_4),
+            Charset::Iso8859_5 => Some(&ISO_8859_5),
+            Charset::Iso8859_6 => Some(&ISO_8859_6),
+            Charset::Iso8859_7 => Some(&ISO_8859_7),
+            Charset::Iso8859_8 => Some(&ISO_8859_8),
+            Charset::Iso8859_9 => Some(&ISO_8859_9),
+            Charset::Iso8859_10 => Some(&ISO_8859_10),
+            Charset::Iso8859_11 => Some(&ISO_8859_11),
+            Charset::Iso8859_13 => Some(&ISO_8859_13),
+            Charset::Iso8859_14 => Some(&ISO_8859_14),
+            Charset::Iso8859_15 => Some(&ISO_8859_15),
+            Charset::Iso8859_16 => Some(&ISO_8859_16),
+            Charset::Windows1250 => Some(&WINDOWS_1250),
+            Charset::Windows1251 => Some(&WINDOWS_1251),
+            Charset::Windows1252 => Some(&WINDOWS_1252),
+            _ => None,
+        }
+    }
+
+    fn double_byte_table(&self) -> Option<&'static [(u16, u16)]> {
+        match self {
+            Charset::ShiftJis => Some(&SHIFT_JIS),
+            Charset::Big5 => Some(&BIG5),
+            _ => None,
+        }
+    }
+
+    fn double_byte_decode_table(&self) -> Option<&'static [(u16, u16)]> {
+        match self {
+            Charset::ShiftJis => Some(&SHIFT_JIS_DECODE),
+            Charset::Big5 => Some(&BIG5_DECODE),
+            _ => None,
+        }
+    }
+
+    /// Transcodes text to the character set
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text to transcode
+    ///
+    /// # Returns
+    ///
+    /// The encoded bytes, or `None` if a character cannot be represented in the character set
+    pub fn encode(&self, input: &str) -> Option<Vec<u8>> {
+        if let Charset::Utf8 = self {
+            return Some(input.as_bytes().to_vec());
+        }
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(input.len());
+
+        for character in input.chars() {
+            if character.is_ascii() {
+                bytes.push(character as u8);
+                continue;
+            }
+
+            let code_point = u16::try_from(character as u32).ok()?;
+
+            if let Some(table) = self.single_byte_table() {
+                let index = table.iter().position(|unicode| *unicode == code_point)?;
+                bytes.push(0x80 + index as u8);
+            } else if *self == Charset::ShiftJis && (0xFF61..=0xFF9F).contains(&code_point) {
+                // Half width katakana are single bytes in Shift JIS
+                bytes.push((code_point - 0xFF61 + 0xA1) as u8);
+            } else {
+                let table = self.double_byte_table()?;
+                let index = table
+                    .binary_search_by_key(&code_point, |(unicode, _)| *unicode)
+                    .ok()?;
+
+                bytes.push((table[index].1 >> 8) as u8);
+                bytes.push(table[index].1 as u8);
+            }
+        }
+
+        Some(bytes)
+    }
+
+    /// Transcodes bytes in the character set back to text
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The bytes read from ECI designated byte mode data
+    ///
+    /// # Returns
+    ///
+    /// The decoded text, bytes that are not valid in the character set become U+FFFD
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        if let Charset::Utf8 = self {
+            return String::from_utf8_lossy(bytes).into_owned();
+        }
+
+        let mut output = String::with_capacity(bytes.len());
+        let mut index = 0;
+
+        while index < bytes.len() {
+            let byte = bytes[index];
+            index += 1;
+
+            if byte < 0x80 {
+                output.push(byte as char);
+                continue;
+            }
+
+            let code_point = if let Some(table) = self.single_byte_table() {
+                table[(byte - 0x80) as usize]
+            } else if *self == Charset::ShiftJis && (0xA1..=0xDF).contains(&byte) {
+                byte as u16 - 0xA1 + 0xFF61
+            } else if let (Some(table), Some(trail)) =
+                (self.double_byte_decode_table(), bytes.get(index))
+            {
+                index += 1;
+
+                let code = ((byte as u16) << 8) | *trail as u16;
+                table
+                    .binary_search_by_key(&code, |(double_byte, _)| *double_byte)
+                    .map_or(0, |index| table[index].1)
+            } else {
+                0
+            };
+
+            let character = char::from_u32(code_point as u32).filter(|_| code_point != 0);
+            output.push(character.unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+
+        output
+    }
+
+    /// Picks the character set that encodes the text in the fewest bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text to transcode
+    ///
+    /// # Returns
+    ///
+    /// The chosen `Charset` with the encoded bytes, UTF-8 is used when no legacy character set
+    /// can represent the text
+    pub fn most_compact(input: &str) -> (Charset, Vec<u8>) {
+        let mut best = (Charset::Utf8, input.as_bytes().to_vec());
+
+        for charset in Charset::ALL {
+            if let Some(bytes) = charset.encode(input) {
+                if bytes.len() < best.1.len() {
+                    best = (charset, bytes);
+                }
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_compact_charset() {
+        assert_eq!(
+            Charset::most_compact("Grüße"),
+            (Charset::Iso8859_1, vec![0x47, 0x72, 0xFC, 0xDF, 0x65])
+        );
+        assert_eq!(Charset::most_compact("Привет").0, Charset::Iso8859_5);
+        assert_eq!(Charset::most_compact("Ελληνικά").0, Charset::Iso8859_7);
+        assert_eq!(Charset::most_compact("テスト").0, Charset::ShiftJis);
+        assert_eq!(Charset::most_compact("說話").0, Charset::Big5);
+        assert_eq!(Charset::most_compact("😀").0, Charset::Utf8);
+    }
+
+    #[test]
+    fn test_charset_round_trip() {
+        for (charset, text) in [
+            (Charset::Iso8859_2, "Zażółć gęślą jaźń"),
+            (Charset::Windows1252, "€ – café"),
+            (Charset::ShiftJis, "日本語ｶﾀｶﾅ"),
+            (Charset::Big5, "中文字"),
+        ] {
+            let bytes = charset.encode(text).unwrap();
+            assert_eq!(charset.decode(&bytes), text);
+        }
+
+        assert_eq!(Charset::Iso8859_1.encode("€"), None);
+        assert_eq!(Charset::from_designator(26), Some(Charset::Utf8));
+    }
+
+    #[test]
+    fn test_double_byte_decode() {
+        // Every character of the double byte tables decodes back to itself
+        for charset in [Charset::ShiftJis, Charset::Big5] {
+            let text: String = charset
+                .double_byte_table()
+                .unwrap()
+                .iter()
+                .filter_map(|(unicode, _)| char::from_u32(*unicode as u32))
+                .collect();
+            let bytes = charset.encode(&text).unwrap();
+            assert_eq!(charset.decode(&bytes), text);
+        }
+
+        // A code missing from the table becomes U+FFFD
+        assert_eq!(Charset::ShiftJis.decode(&[0x85, 0x40]), "\u{FFFD}");
+    }
+}
@@ -0,0 +1,473 @@
+//! # GS1 Element Strings
+//!
+//! Builds GS1 element strings from Application Identifier/value pairs so they can be encoded
+//! behind the FNC1 first position mode indicator.
+//!
+//! Every value is validated against the format of its Application Identifier (character set,
+//! fixed or variable length, check digit and dates). A group separator is inserted after every
+//! element whose Application Identifier does not have a predefined length, except the last one.
+//!
+//! ```rust,ignore
+//! let element_string = build_element_string(&[("01", "09506000134352"), ("10", "ABC123")])?;
+//! assert_eq!(element_string, "010950600013435210ABC123");
+//! ```
+
+use std::fmt::Display;
+
+/// The group separator (GS) character used to terminate variable length values
+pub const GROUP_SEPARATOR: char = '\u{1D}';
+
+/// Application Identifier prefixes whose element strings have a predefined length and therefore
+/// never need a group separator after them
+const PREDEFINED_LENGTH_PREFIXES: [&str; 22] = [
+    "00", "01", "02", "03", "04", "11", "12", "13", "14", "15", "16", "17", "18", "19", "20", "31",
+    "32", "33", "34", "35", "36", "41",
+];
+
+/// Errors that can occur while building a GS1 element string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gs1Error {
+    /// No Application Identifier/value pairs were given
+    Empty,
+    /// The Application Identifier is not supported
+    UnknownApplicationIdentifier(String),
+    /// The value does not have a valid length for its Application Identifier
+    InvalidLength { ai: String, length: usize },
+    /// The value contains a character that is not allowed for its Application Identifier
+    InvalidCharacter { ai: String, character: char },
+    /// The trailing check digit of the value is wrong
+    InvalidCheckDigit { ai: String },
+    /// The value is not a valid YYMMDD date
+    InvalidDate { ai: String },
+    /// The element string does not fit in a version 40 QR code
+    DataTooLong,
+}
+
+impl Display for Gs1Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Gs1Error::Empty => write!(f, "no application identifiers given"),
+            Gs1Error::UnknownApplicationIdentifier(ai) => {
+                write!(f, "unknown application identifier ({})", ai)
+            }
+            Gs1Error::InvalidLength { ai, length } => {
+                write!(
+                    f,
+                    "invalid length {} for application identifier ({})",
+                    length, ai
+                )
+            }
+            Gs1Error::InvalidCharacter { ai, character } => write!(
+                f,
+                "invalid character {:?} for application identifier ({})",
+                character, ai
+            ),
+            Gs1Error::InvalidCheckDigit { ai } => {
+                write!(f, "invalid check digit for application identifier ({})", ai)
+            }
+            Gs1Error::InvalidDate { ai } => {
+                write!(f, "invalid date for application identifier ({})", ai)
+            }
+            Gs1Error::DataTooLong => write!(f, "data does not fit in a QR code"),
+        }
+    }
+}
+
+impl std::error::Error for Gs1Error {}
+
+/// Characters a value may be made of
+#[derive(Clone, Copy)]
+enum ValueCharset {
+    /// Digits only
+    Numeric,
+    /// GS1 AI encodable character set 82
+    Alphanumeric,
+}
+
+/// Number of characters a value may have
+#[derive(Clone, Copy)]
+enum ValueLength {
+    Fixed(usize),
+    Variable(usize),
+}
+
+/// Format definition of an Application Identifier
+struct ApplicationIdentifier {
+    /// Leading digits that identify the Application Identifier
+    prefix: &'static str,
+    /// Total number of digits of the Application Identifier
+    digits: usize,
+    charset: ValueCharset,
+    length: ValueLength,
+    /// The last digit of the value is a GS1 mod 10 check digit
+    check_digit: bool,
+    /// The value is a YYMMDD date
+    date: bool,
+}
+
+macro_rules! ai {
+    ($prefix:literal, $digits:literal, N, $length:literal) => {
+        ai!(
+            $prefix,
+            $digits,
+            ValueCharset::Numeric,
+            ValueLength::Fixed($length),
+            false,
+            false
+        )
+    };
+    ($prefix:literal, $digits:literal, N, $length:literal, check) => {
+        ai!(
+            $prefix,
+            $digits,
+            ValueCharset::Numeric,
+            ValueLength::Fixed($length),
+            true,
+            false
+        )
+    };
+    ($prefix:literal, $digits:literal, N, $length:literal, date) => {
+        ai!(
+            $prefix,
+            $digits,
+            ValueCharset::Numeric,
+            ValueLength::Fixed($length),
+            false,
+            true
+        )
+    };
+    ($prefix:literal, $digits:literal, N.., $length:literal) => {
+        ai!(
+            $prefix,
+            $digits,
+            ValueCharset::Numeric,
+            ValueLength::Variable($length),
+            false,
+            false
+        )
+    };
+    ($prefix:literal, $digits:literal, X.., $length:literal) => {
+        ai!(
+            $prefix,
+            $digits,
+            ValueCharset::Alphanumeric,
+            ValueLength::Variable($length),
+            false,
+            false
+        )
+    };
+    ($prefix:literal, $digits:literal, $charset:expr, $length:expr, $check_digit:literal, $date:literal) => {
+        ApplicationIdentifier {
+            prefix: $prefix,
+            digits: $digits,
+            charset: $charset,
+            length: $length,
+            check_digit: $check_digit,
+            date: $date,
+        }
+    };
+}
+
+/// Supported Application Identifiers, more specific prefixes come first
+const APPLICATION_IDENTIFIERS: [ApplicationIdentifier; 85] = [
+    ai!("00", 2, N, 18, check), // SSCC
+    ai!("01", 2, N, 14, check), // GTIN
+    ai!("02", 2, N, 14, check), // GTIN of contained trade items
+    ai!("10", 2, X.., 20),      // Batch or lot number
+    ai!("11", 2, N, 6, date),   // Production date
+    ai!("12", 2, N, 6, date),   // Due date
+    ai!("13", 2, N, 6, date),   // Packaging date
+    ai!("15", 2, N, 6, date),   // Best before date
+    ai!("16", 2, N, 6, date),   // Sell by date
+    ai!("17", 2, N, 6, date),   // Expiration date
+    ai!("20", 2, N, 2),         // Internal product variant
+    ai!("21", 2, X.., 20),      // Serial number
+    ai!("22", 2, X.., 20),      // Consumer product variant
+    ai!("235", 3, X.., 28),     // Third party controlled serialised extension
+    ai!("240", 3, X.., 30),     // Additional product identification
+    ai!("241", 3, X.., 30),     // Customer part number
+    ai!("242", 3, N.., 6),      // Made-to-order variation number
+    ai!("243", 3, X.., 20),     // Packaging component number
+    ai!("250", 3, X.., 30),     // Secondary serial number
+    ai!("251", 3, X.., 30),     // Reference to source entity
+    ai!("254", 3, X.., 20),     // GLN extension component
+    ai!("30", 2, N.., 8),       // Variable count of items
+    ai!("310", 4, N, 6),        // Trade measures (310n - 316n)
+    ai!("311", 4, N, 6),
+    ai!("312", 4, N, 6),
+    ai!("313", 4, N, 6),
+    ai!("314", 4, N, 6),
+    ai!("315", 4, N, 6),
+    ai!("316", 4, N, 6),
+    ai!("32", 4, N, 6),  // Trade measures (320n - 329n)
+    ai!("330", 4, N, 6), // Logistic measures (330n - 337n)
+    ai!("331", 4, N, 6),
+    ai!("332", 4, N, 6),
+    ai!("333", 4, N, 6),
+    ai!("334", 4, N, 6),
+    ai!("335", 4, N, 6),
+    ai!("336", 4, N, 6),
+    ai!("337", 4, N, 6),
+    ai!("34", 4, N, 6),  // Logistic measures (340n - 349n)
+    ai!("350", 4, N, 6), // Trade and logistic measures (350n - 357n)
+    ai!("351", 4, N, 6),
+    ai!("352", 4, N, 6),
+    ai!("353", 4, N, 6),
+    ai!("354", 4, N, 6),
+    ai!("355", 4, N, 6),
+    ai!("356", 4, N, 6),
+    ai!("357", 4, N, 6),
+    ai!("36", 4, N, 6),          // Trade and logistic measures (360n - 369n)
+    ai!("37", 2, N.., 8),        // Count of trade items
+    ai!("390", 4, N.., 15),      // Amount payable, single monetary area
+    ai!("391", 4, N.., 18),      // Amount payable with ISO currency code
+    ai!("392", 4, N.., 15),      // Price, single monetary area
+    ai!("393", 4, N.., 18),      // Price with ISO currency code
+    ai!("400", 3, X.., 30),      // Customer's purchase order number
+    ai!("401", 3, X.., 30),      // Global Identification Number for Consignment
+    ai!("402", 3, N, 17, check), // Global Shipment Identification Number
+    ai!("403", 3, X.., 30),      // Routing code
+    ai!("410", 3, N, 13, check), // Global Location Numbers (410 - 417)
+    ai!("411", 3, N, 13, check),
+    ai!("412", 3, N, 13, check),
+    ai!("413", 3, N, 13, check),
+    ai!("414", 3, N, 13, check),
+    ai!("415", 3, N, 13, check),
+    ai!("416", 3, N, 13, check),
+    ai!("417", 3, N, 13, check),
+    ai!("420", 3, X.., 20), // Ship to postal code
+    ai!("422", 3, N, 3),    // Country of origin
+    ai!("423", 3, N.., 15), // Countries of initial processing
+    ai!("424", 3, N, 3),    // Country of processing
+    ai!("425", 3, N.., 15), // Countries of disassembly
+    ai!("426", 3, N, 3),    // Country covering full process chain
+    ai!("7001", 4, N, 13),  // NATO Stock Number
+    ai!("7003", 4, N, 10),  // Expiration date and time
+    ai!("710", 3, X.., 20), // National Healthcare Reimbursement Number (710 - 715)
+    ai!("711", 3, X.., 20),
+    ai!("712", 3, X.., 20),
+    ai!("713", 3, X.., 20),
+    ai!("714", 3, X.., 20),
+    ai!("715", 3, X.., 20),
+    ai!("8004", 4, X.., 30),      // Global Individual Asset Identifier
+    ai!("8017", 4, N, 18, check), // Global Service Relation Number (provider)
+    ai!("8018", 4, N, 18, check), // Global Service Relation Number (recipient)
+    ai!("8020", 4, X.., 25),      // Payment slip reference number
+    ai!("90", 2, X.., 30),        // Information mutually agreed between trading partners
+    ai!("9", 2, X.., 90),         // Company internal information (91 - 99)
+];
+
+/// Checks if a character is part of the GS1 AI encodable character set 82
+fn is_gs1_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || "!\"%&'()*+,-./:;<=>?_".contains(character)
+}
+
+/// Validates the trailing GS1 mod 10 check digit of a numeric value
+fn has_valid_check_digit(value: &str) -> bool {
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    let Some((check_digit, data)) = digits.split_last() else {
+        return false;
+    };
+
+    // Weights alternate 3, 1, 3, ... starting from the digit next to the check digit
+    let sum: u32 = data
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+
+    (10 - sum % 10) % 10 == *check_digit
+}
+
+/// Validates a YYMMDD date, a day of 00 means the end of the month
+fn is_valid_date(value: &str) -> bool {
+    let (Ok(year), Ok(month), Ok(day)) = (
+        value[..2].parse::<u8>(),
+        value[2..4].parse::<u8>(),
+        value[4..6].parse::<u8>(),
+    ) else {
+        return false;
+    };
+
+    // Every year that YY can stand for within the 50 year window around today is a leap year
+    // exactly when YY is a multiple of 4, including 2000
+    let days_in_month = match month {
+        2 if year % 4 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+
+    day <= days_in_month
+}
+
+impl ApplicationIdentifier {
+    fn find(ai: &str) -> Result<&'static ApplicationIdentifier, Gs1Error> {
+        if !ai.chars().all(|c| c.is_ascii_digit()) {
+            return Err(Gs1Error::UnknownApplicationIdentifier(ai.to_string()));
+        }
+
+        APPLICATION_IDENTIFIERS
+            .iter()
+            .find(|definition| definition.digits == ai.len() && ai.starts_with(definition.prefix))
+            .ok_or_else(|| Gs1Error::UnknownApplicationIdentifier(ai.to_string()))
+    }
+
+    fn validate(&self, ai: &str, value: &str) -> Result<(), Gs1Error> {
+        let length = value.chars().count();
+        let valid_length = match self.length {
+            ValueLength::Fixed(fixed) => length == fixed,
+            ValueLength::Variable(max) => length > 0 && length <= max,
+        };
+
+        if !valid_length {
+            return Err(Gs1Error::InvalidLength {
+                ai: ai.to_string(),
+                length,
+            });
+        }
+
+        let invalid_character = value.chars().find(|character| match self.charset {
+            ValueCharset::Numeric => !character.is_ascii_digit(),
+            ValueCharset::Alphanumeric => !is_gs1_character(*character),
+        });
+
+        if let Some(character) = invalid_character {
+            return Err(Gs1Error::InvalidCharacter {
+                ai: ai.to_string(),
+                character,
+            });
+        }
+
+        if self.check_digit && !has_valid_check_digit(value) {
+            return Err(Gs1Error::InvalidCheckDigit { ai: ai.to_string() });
+        }
+
+        if self.date && !is_valid_date(value) {
+            return Err(Gs1Error::InvalidDate { ai: ai.to_string() });
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a GS1 element string from Application Identifier/value pairs
+///
+/// # Arguments
+///
+/// * `elements` - Application Identifier/value pairs such as `("01", "09506000134352")`
+///
+/// # Returns
+///
+/// The element string with `GROUP_SEPARATOR` after every variable length value that is
+/// followed by another element, or a `Gs1Error` if any value is invalid
+pub fn build_element_string(elements: &[(&str, &str)]) -> Result<String, Gs1Error> {
+    if elements.is_empty() {
+        return Err(Gs1Error::Empty);
+    }
+
+    let mut element_string = String::new();
+
+    for (index, (ai, value)) in elements.iter().enumerate() {
+        ApplicationIdentifier::find(ai)?.validate(ai, value)?;
+
+        element_string.push_str(ai);
+        element_string.push_str(value);
+
+        let predefined_length = PREDEFINED_LENGTH_PREFIXES
+            .iter()
+            .any(|prefix| ai.starts_with(prefix));
+
+        if !predefined_length && index + 1 < elements.len() {
+            element_string.push(GROUP_SEPARATOR);
+        }
+    }
+
+    Ok(element_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_string_separators() {
+        let element_string = build_element_string(&[
+            ("01", "09506000134352"),
+            ("17", "201225"),
+            ("10", "ABC123"),
+            ("21", "12345"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            element_string,
+            "01095060001343521720122510ABC123\u{1D}2112345"
+        );
+    }
+
+    #[test]
+    fn test_element_validation() {
+        assert_eq!(
+            build_element_string(&[("01", "09506000134353")]),
+            Err(Gs1Error::InvalidCheckDigit {
+                ai: "01".to_string()
+            })
+        );
+        assert_eq!(
+            build_element_string(&[("01", "0950600013435")]),
+            Err(Gs1Error::InvalidLength {
+                ai: "01".to_string(),
+                length: 13
+            })
+        );
+        assert_eq!(
+            build_element_string(&[("17", "201325")]),
+            Err(Gs1Error::InvalidDate {
+                ai: "17".to_string()
+            })
+        );
+        assert_eq!(
+            build_element_string(&[("10", "AB#1")]),
+            Err(Gs1Error::InvalidCharacter {
+                ai: "10".to_string(),
+                character: '#'
+            })
+        );
+        assert_eq!(
+            build_element_string(&[("05", "1")]),
+            Err(Gs1Error::UnknownApplicationIdentifier("05".to_string()))
+        );
+        assert!(build_element_string(&[("3103", "000750")]).is_ok());
+    }
+
+    #[test]
+    fn test_dates_and_assigned_identifiers() {
+        // Days are checked against the month, February has 29 days in leap years
+        for date in ["240229", "250430", "251231", "250200"] {
+            assert!(build_element_string(&[("17", date)]).is_ok(), "{}", date);
+        }
+        for date in ["250229", "250431", "250631", "251232"] {
+            assert_eq!(
+                build_element_string(&[("17", date)]),
+                Err(Gs1Error::InvalidDate {
+                    ai: "17".to_string()
+                })
+            );
+        }
+
+        // Only the assigned Application Identifiers of a range are accepted
+        assert!(build_element_string(&[("417", "9501101530003")]).is_ok());
+        assert!(build_element_string(&[("3163", "000750")]).is_ok());
+        assert!(build_element_string(&[("3292", "000750")]).is_ok());
+        for ai in ["418", "419", "3173", "3383", "3583"] {
+            assert_eq!(
+                build_element_string(&[(ai, "000750")]),
+                Err(Gs1Error::UnknownApplicationIdentifier(ai.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! # Halftone
+//!
+//! Encodes data so the modules follow a picture wherever the standard leaves a choice. The pad
+//! codewords after the terminator are ignored by decoders, so their bits are set to the modules
+//! of the picture, and every data mask is tried to keep the one whose modules match the picture
+//! best. The error correction codewords are computed as usual, so the symbol stays valid.
+//!
+//! ```rust,ignore
+//! let picture = GrayImage::read_netpbm(File::open("picture.pgm")?)?;
+//! let bit_map = create_picture_bit_map("HELLO", ErrorCorrectionLevel::L, &picture);
+//! ```
+
+use super::constants::BYTE_SIZE;
+use super::{data_modules, mask_applies, ErrorCorrectionLevel, QRMode};
+use crate::bit_utils::{bit::Bit, bit_string::BitString, bitmap::*};
+use crate::render::netpbm::GrayImage;
+
+/// Lists the modules of every data codeword, in the order of the data codewords before
+/// interleaving
+///
+/// # Arguments
+///
+/// * `bit_map` - A placed QR code of the version with the roles of its modules
+/// * `error_correction_level` - Error correction level of the symbol
+/// * `version` - Version of the symbol (0 - 39)
+///
+/// # Returns
+///
+/// The 8 modules of every data codeword from the most significant bit
+fn data_codeword_modules(
+    bit_map: &BitMap,
+    error_correction_level: &ErrorCorrectionLevel,
+    version: usize,
+) -> Vec<Vec<(usize, usize)>> {
+    let (num_blocks_group_1, num_code_words_group_1, num_blocks_group_2, num_code_words_group_2) =
+        error_correction_level.get_block_data(version);
+
+    let block_lengths: Vec<usize> = std::iter::repeat_n(num_code_words_group_1, num_blocks_group_1)
+        .chain(std::iter::repeat_n(
+            num_code_words_group_2,
+            num_blocks_group_2,
+        ))
+        .collect();
+    let num_codewords: usize = block_lengths.iter().sum();
+
+    let modules = data_modules(bit_map);
+    let mut codeword_modules = vec![Vec::new(); num_codewords];
+    let mut interleaved = 0;
+
+    // The blocks take turns, so the index of a codeword is its block offset plus its position
+    for i in 0..num_code_words_group_1.max(num_code_words_group_2) {
+        let mut offset = 0;
+
+        for length in &block_lengths {
+            if i < *length {
+                let bits = &modules[interleaved * BYTE_SIZE..(interleaved + 1) * BYTE_SIZE];
+                codeword_modules[offset + i] = bits.to_vec();
+                interleaved += 1;
+            }
+
+            offset += length;
+        }
+    }
+
+    codeword_modules
+}
+
+/// Encodes data into a QR code whose pad codewords and data mask follow a picture
+///
+/// # Arguments
+///
+/// * `data` - The string data to be encoded in the QR code
+/// * `error_correction_level` - The error correction level for redundancy and recovery
+/// * `picture` - Grayscale picture stretched over the symbol
+///
+/// # Returns
+///
+/// A `BitMap` whose data modules match the dark areas of the picture as far as the data allows
+pub(crate) fn create_picture_bit_map(
+    data: &str,
+    error_correction_level: ErrorCorrectionLevel,
+    picture: &GrayImage,
+) -> BitMap {
+    let mut qr_mode = QRMode::analyze_data(data, error_correction_level);
+    let bits = qr_mode.encode();
+    let version = qr_mode.version();
+    let codewords = bits.as_bytes().to_vec();
+
+    // Everything after the terminator is padding the decoder skips
+    let terminated = (qr_mode.bit_length(version) + 4).min(codewords.len() * BYTE_SIZE);
+    let first_free = terminated.div_ceil(BYTE_SIZE);
+
+    let blocks = qr_mode.generate_error_correction(bits);
+    let layout = qr_mode.create_bit_map(qr_mode.structure_codewords(blocks));
+    let size = layout.size();
+    let modules = data_codeword_modules(&layout, qr_mode.error_correction_level(), version);
+    let dark = picture
+        .resample(size)
+        .into_iter()
+        .map(|luma| luma < 128.0)
+        .collect::<Vec<bool>>();
+
+    (0..8)
+        .map(|mask| {
+            let mut codewords = codewords.clone();
+
+            // The bits are placed before masking, so they are the masked picture
+            for (codeword, modules) in codewords.iter_mut().zip(&modules).skip(first_free) {
+                *codeword = modules.iter().fold(0, |byte, &(row, column)| {
+                    (byte << 1)
+                        | (dark[row * size + column] != mask_applies(mask, row, column)) as u8
+                });
+            }
+
+            let blocks = qr_mode.generate_error_correction(BitString::from_vec(codewords));
+            let bit_map = qr_mode.create_masked_bit_map(qr_mode.structure_codewords(blocks), mask);
+
+            let matches = (0..size * size)
+                .filter(|index| {
+                    let (row, column) = (index / size, index % size);
+
+                    bit_map.role(row, column) == ModuleRole::Data
+                        && (bit_map.get(row, column) == Bit::One) == dark[*index]
+                })
+                .count();
+
+            (matches, bit_map)
+        })
+        .max_by_key(|(matches, _)| *matches)
+        .map(|(_, bit_map)| bit_map)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_picture_bit_map() {
+        // A black picture asks for dark modules everywhere
+        let picture = GrayImage {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 4],
+        };
+        let bit_map = create_picture_bit_map("hi", ErrorCorrectionLevel::L, &picture);
+
+        let dark = |bit_map: &BitMap| {
+            data_modules(bit_map)
+                .into_iter()
+                .filter(|(row, column)| bit_map.get(*row, *column) == Bit::One)
+                .count()
+        };
+        let plain = crate::create_qr_code("hi", ErrorCorrectionLevel::L);
+        assert!(dark(&bit_map) > dark(&plain) + 40);
+
+        // Unmasking with the mask in the format information gives back the codewords
+        let format = (0..6).fold(0, |bits, column| {
+            (bits << 1) | (bit_map.get(8, column) == Bit::One) as u32
+        });
+        let mask = (((format ^ (0x5412 >> 9)) >> 1) & 0b111) as u8;
+
+        let codewords: Vec<u8> = data_modules(&bit_map)
+            .chunks(BYTE_SIZE)
+            .take(19)
+            .map(|modules| {
+                modules.iter().fold(0, |byte, &(row, column)| {
+                    let bit = bit_map.get(row, column) == Bit::One;
+                    (byte << 1) | (bit != mask_applies(mask, row, column)) as u8
+                })
+            })
+            .collect();
+
+        // Byte mode, 2 characters, "hi" and the terminator
+        assert_eq!(codewords[..4], [0x40, 0x26, 0x86, 0x90]);
+    }
+}
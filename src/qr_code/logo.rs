@@ -0,0 +1,235 @@
+//! # Logo
+//!
+//! Reserves a centred area of a QR code for a logo. The modules under the logo are lost to the
+//! scanner, so the codewords they belong to are counted for every Reed-Solomon block and
+//! compared with the number of codewords the block can correct. A logo is only accepted while
+//! every block keeps a safety margin of its error correction capacity, and only data modules
+//! and alignment patterns may be covered.
+//!
+//! ```rust,ignore
+//! let options = LogoOptions {
+//!     shape: LogoShape::Circle { diameter: 9 },
+//!     ..LogoOptions::default()
+//! };
+//! let qr_code = create_logo_qr_code("https://example.com", ErrorCorrectionLevel::M, &options)?;
+//! ```
+
+use super::{data_modules, ErrorCorrectionLevel};
+use crate::bit_utils::{bit::Bit, bitmap::*};
+use std::fmt::Display;
+
+/// Area reserved for a logo in the centre of the symbol, in modules
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum LogoShape {
+    Rectangle { width: usize, height: usize },
+    Circle { diameter: usize },
+}
+
+impl LogoShape {
+    /// Checks whether a module is covered by the logo, even partly
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Width of the symbol in modules
+    /// * `row` - Row index of the module
+    /// * `column` - Column index of the module
+    pub fn covers(&self, size: usize, row: usize, column: usize) -> bool {
+        match *self {
+            LogoShape::Rectangle { width, height } => {
+                let left = size.saturating_sub(width) / 2;
+                let top = size.saturating_sub(height) / 2;
+
+                (left..left + width).contains(&column) && (top..top + height).contains(&row)
+            }
+            LogoShape::Circle { diameter } => {
+                let centre = size as f64 / 2.0;
+                let radius = diameter as f64 / 2.0;
+
+                // Distance from the centre to the closest point of the module
+                let dx = centre.clamp(column as f64, column as f64 + 1.0) - centre;
+                let dy = centre.clamp(row as f64, row as f64 + 1.0) - centre;
+
+                dx * dx + dy * dy < radius * radius
+            }
+        }
+    }
+}
+
+/// Options for reserving a logo area
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct LogoOptions {
+    /// Size and shape of the area
+    pub shape: LogoShape,
+    /// Share of the correctable codewords of every block the logo may damage, between 0 and 1
+    pub max_damage: f64,
+    /// Raise the error correction level when the logo damages too many codewords
+    pub raise_error_correction: bool,
+    /// Raise the version once the error correction level cannot be raised any further
+    pub raise_version: bool,
+}
+
+impl Default for LogoOptions {
+    /// A 7 x 7 module square that may use half of the error correction capacity
+    fn default() -> Self {
+        Self {
+            shape: LogoShape::Rectangle {
+                width: 7,
+                height: 7,
+            },
+            max_damage: 0.5,
+            raise_error_correction: true,
+            raise_version: true,
+        }
+    }
+}
+
+/// Errors that can occur while reserving a logo area
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogoError {
+    /// The logo covers a module of a function pattern other than an alignment pattern
+    CoversFunctionPattern { row: usize, column: usize },
+    /// A block has more damaged codewords than the safety margin allows
+    TooMuchDamage { damaged: usize, allowed: usize },
+}
+
+impl Display for LogoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LogoError::CoversFunctionPattern { row, column } => write!(
+                f,
+                "logo covers the function pattern module at row {}, column {}",
+                row, column
+            ),
+            LogoError::TooMuchDamage { damaged, allowed } => write!(
+                f,
+                "logo damages {} codewords of a block, at most {} allowed",
+                damaged, allowed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LogoError {}
+
+/// Lists the block of every codeword in the order they are interleaved
+///
+/// # Arguments
+///
+/// * `error_correction_level` - Error correction level of the symbol
+/// * `version` - Version of the symbol (0 - 39)
+fn codeword_blocks(error_correction_level: &ErrorCorrectionLevel, version: usize) -> Vec<usize> {
+    let (num_blocks_group_1, num_code_words_group_1, num_blocks_group_2, num_code_words_group_2) =
+        error_correction_level.get_block_data(version);
+
+    let block_lengths: Vec<usize> = std::iter::repeat_n(num_code_words_group_1, num_blocks_group_1)
+        .chain(std::iter::repeat_n(
+            num_code_words_group_2,
+            num_blocks_group_2,
+        ))
+        .collect();
+
+    let mut blocks = Vec::new();
+
+    // The data codewords are interleaved first, the shorter blocks run out first
+    for i in 0..num_code_words_group_1.max(num_code_words_group_2) {
+        for (block, length) in block_lengths.iter().enumerate() {
+            if i < *length {
+                blocks.push(block);
+            }
+        }
+    }
+
+    for _ in 0..error_correction_level.get_num_error_correction_codewords(version) {
+        blocks.extend(0..block_lengths.len());
+    }
+
+    blocks
+}
+
+/// Counts the codewords of every block with at least one module under a logo
+///
+/// # Arguments
+///
+/// * `bit_map` - The placed QR code with its module roles
+/// * `error_correction_level` - Error correction level of the symbol
+/// * `version` - Version of the symbol (0 - 39)
+/// * `shape` - Area of the logo
+pub(crate) fn block_damage(
+    bit_map: &BitMap,
+    error_correction_level: &ErrorCorrectionLevel,
+    version: usize,
+    shape: &LogoShape,
+) -> Vec<usize> {
+    let size = bit_map.size();
+    let blocks = codeword_blocks(error_correction_level, version);
+
+    let mut damaged = vec![false; blocks.len()];
+    for (index, (row, column)) in data_modules(bit_map).into_iter().enumerate() {
+        // The remainder bits after the last codeword do not matter
+        if index / 8 < blocks.len() && shape.covers(size, row, column) {
+            damaged[index / 8] = true;
+        }
+    }
+
+    let mut damage = vec![0; blocks.iter().max().map_or(0, |block| block + 1)];
+    for (block, _) in blocks.iter().zip(damaged).filter(|(_, damaged)| *damaged) {
+        damage[*block] += 1;
+    }
+
+    damage
+}
+
+/// Clears the data modules under a logo and marks them with the logo role
+///
+/// # Returns
+///
+/// A `LogoError` if the logo covers a function pattern other than an alignment pattern
+pub(crate) fn clear_logo_area(bit_map: &mut BitMap, shape: &LogoShape) -> Result<(), LogoError> {
+    let size = bit_map.size();
+
+    for row in 0..size {
+        for column in 0..size {
+            if !shape.covers(size, row, column) {
+                continue;
+            }
+
+            match bit_map.role(row, column) {
+                ModuleRole::Data | ModuleRole::Logo => {
+                    bit_map.set(row, column, Bit::Zero);
+                    bit_map.set_role(row, column, ModuleRole::Logo);
+                }
+                ModuleRole::Alignment => {}
+                _ => return Err(LogoError::CoversFunctionPattern { row, column }),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logo_shapes() {
+        let square = LogoShape::Rectangle {
+            width: 3,
+            height: 3,
+        };
+        assert!(square.covers(21, 9, 9));
+        assert!(square.covers(21, 11, 11));
+        assert!(!square.covers(21, 8, 10));
+
+        let circle = LogoShape::Circle { diameter: 5 };
+        assert!(circle.covers(21, 10, 8));
+        assert!(circle.covers(21, 8, 9));
+        assert!(!circle.covers(21, 8, 7));
+
+        // Version 5-Q has 2 blocks of 15 and 2 of 16 data codewords with 18 EC codewords each
+        let blocks = codeword_blocks(&ErrorCorrectionLevel::Q, 4);
+        assert_eq!(blocks.len(), 2 * 15 + 2 * 16 + 4 * 18);
+        assert_eq!(blocks[56..62], [0, 1, 2, 3, 2, 3]);
+        assert_eq!(blocks[62..66], [0, 1, 2, 3]);
+    }
+}
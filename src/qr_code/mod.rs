@@ -8,6 +8,7 @@
 //!   - Numeric
 //!   - Alphanumeric
 //!   - Byte
+//!   - Hanzi (GB 2312)
 //!
 //! - Supports various QR code versions and error correction levels
 //!
@@ -17,6 +18,11 @@
 //! - Numeric mode: For pure numeric data
 //! - Alphanumeric mode: For data containing alphanumeric characters
 //! - Byte mode: For ASCII data
+//! - Hanzi mode: For Chinese text made of GB 2312 characters, packed into 13 bits each
+//!
+//! Other text is transcoded to the most compact legacy character set (ISO-8859-x, Windows-125x,
+//! Shift JIS or Big5, falling back to UTF-8) and placed in byte mode behind the ECI designator of
+//! that character set
 //!
 //! ## Error Correction
 //!
@@ -26,8 +32,17 @@
 //! - Q: Quartile error correction (25% recovery)
 //! - H: Highest error correction (30% recovery)
 //!
+//! ## GS1
+//!
+//! GS1 element strings are encoded behind the FNC1 first position mode indicator, see the `gs1`
+//! module for the Application Identifier validation. Other industry formats are marked with the
+//! FNC1 second position mode indicator followed by their application indicator
+//!
 //! ## Usage Example
 //!
+//! The stages are best run through the type-state wrappers in the `pipeline` module, which
+//! make running them out of order a compile error
+//!
 //! ```rust 
 //! let qr_mode = QRMode::analyze_data("HELLO WORLD", ErrorCorrectionLevel::M);
 //! let encoded_bits = qr_mode.encode();
@@ -41,8 +56,22 @@ use crate::galios::*;
 
 // Constants
 use crate::qr_code::constants::*;
+use crate::qr_code::gb2312::{GB2312_DECODE_TABLE, GB2312_TABLE};
+
+pub use eci::Charset;
+
+pub use gs1::Gs1Error;
+pub(crate) use halftone::create_picture_bit_map;
+pub use logo::{LogoError, LogoOptions, LogoShape};
 
+mod charsets;
 mod constants;
+mod eci;
+mod gb2312;
+mod gs1;
+mod halftone;
+mod logo;
+pub mod pipeline;
 
 
 /// Represents different encoding modes for QR code generation
@@ -52,6 +81,7 @@ mod constants;
 /// * Numeric: Encodes pure numeric data
 /// * AlphaNumeric: Encodes alphanumeric data
 /// * Byte: Encodes ASCII byte data
+/// * Hanzi: Encodes GB 2312 Chinese characters
 ///
 /// # Methods
 ///
@@ -73,6 +103,7 @@ pub enum QRMode {
     AlphaNumeric(QrCode),
     Byte(QrCode),
     // Kanji(Vec<u16>), // Double byte mode
+    Hanzi(QrCode), // GB 2312 double bytes
 }
 
 /// Represents a QR Code with its data, version, and error correction level
@@ -84,6 +115,64 @@ pub struct QrCode {
     version: usize,
     /// Selected error correction level
     error_correction_level: ErrorCorrectionLevel,
+    /// FNC1 mode indicator placed ahead of the data, if any
+    fnc1: Option<Fnc1>,
+    /// Character set designated with an ECI ahead of the data, if any
+    eci: Option<Charset>,
+}
+
+/// FNC1 mode indicators that mark the data as belonging to an industry specific format
+///
+/// # Variants
+///
+/// * FirstPosition: The data is a GS1 element string (mode indicator 0101)
+/// * SecondPosition: The data follows an AIM approved industry format identified by an
+///   application indicator (mode indicator 1001)
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let fnc1 = Fnc1::application_indicator("37").unwrap();
+/// assert_eq!(fnc1, Fnc1::SecondPosition(37));
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Fnc1 {
+    /// GS1 formatted data (mode indicator 0101)
+    FirstPosition,
+    /// Industry format data (mode indicator 1001) with the 8 bit application indicator codeword
+    SecondPosition(u8),
+}
+
+impl Fnc1 {
+    /// Creates an FNC1 second position indicator from an application indicator
+    ///
+    /// # Arguments
+    ///
+    /// * `indicator` - Either a two digit number (`"00"` to `"99"`) or a single ASCII letter
+    ///
+    /// # Returns
+    ///
+    /// `Fnc1::SecondPosition` with the encoded indicator (the number itself, or the ASCII value
+    /// of the letter + 100), or `None` if the indicator is not valid
+    pub fn application_indicator(indicator: &str) -> Option<Fnc1> {
+        let bytes = indicator.as_bytes();
+
+        match bytes {
+            [tens, ones] if tens.is_ascii_digit() && ones.is_ascii_digit() => {
+                Some(Fnc1::SecondPosition((tens - b'0') * 10 + (ones - b'0')))
+            }
+            [letter] if letter.is_ascii_alphabetic() => Some(Fnc1::SecondPosition(letter + 100)),
+            _ => None,
+        }
+    }
+
+    /// Number of bits the mode indicator (and application indicator) takes up
+    fn bit_length(&self) -> usize {
+        match self {
+            Fnc1::FirstPosition => 4,
+            Fnc1::SecondPosition(_) => 12,
+        }
+    }
 }
 
 
@@ -110,7 +199,7 @@ pub struct QrCode {
 /// ```rust 
 /// let max_numeric_size = ErrorCorrectionLevel::M.get_numeric_version_size(1); 
 /// ``` 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ErrorCorrectionLevel {
     /// Lowest error correction (approximately 7% of codewords can be restored)
     L,
@@ -220,6 +309,25 @@ impl ErrorCorrectionLevel {
 
 // ------------------ Helper functions ----------------------
 
+/// Converts an alphanumeric character to its value in the 45 character alphanumeric table
+fn alphanumeric_value(character: u8) -> u8 {
+    if character > 47 && character < 58 {
+        character - 48
+    } else if character > 64 && character < 91 {
+        character - 55
+    } else if character == 32 {
+        36
+    } else if character > 35 && character < 38 {
+        character + 1
+    } else if character > 41 && character < 44 {
+        character - 3
+    } else if character > 44 && character < 48 {
+        character - 4
+    } else {
+        44
+    }
+}
+
 fn is_numeric(input: &str) -> bool {
     for character in input.chars() {
         if !character.is_ascii_digit() {
@@ -237,7 +345,7 @@ fn is_alphanumeric(input: &str) -> bool {
         || (character > 35 && character < 38)   // $ and %
         || (character > 41 && character < 44)   // * and +
         || (character > 44 && character < 59)   // -, ., /, numerals, and :
-        || (character > 64 && character < 90)
+        || (character > 64 && character < 91)
             // Capital Letters
         ) {
             return false;
@@ -247,6 +355,52 @@ fn is_alphanumeric(input: &str) -> bool {
     true
 }
 
+/// Converts the input to GB 2312 double bytes if every character can be encoded in Hanzi mode
+fn gb2312_encode(input: &str) -> Option<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::with_capacity(input.len());
+
+    for character in input.chars() {
+        let code_point = u16::try_from(character as u32).ok()?;
+        let index = GB2312_TABLE
+            .binary_search_by_key(&code_point, |(unicode, _)| *unicode)
+            .ok()?;
+
+        let gb2312 = GB2312_TABLE[index].1;
+        data.push((gb2312 >> 8) as u8);
+        data.push(gb2312 as u8);
+    }
+
+    Some(data)
+}
+
+/// Converts GB 2312 double bytes, such as the data of a Hanzi mode segment, back to text
+///
+/// # Arguments
+///
+/// * `data` - The double byte GB 2312 codes
+///
+/// # Returns
+///
+/// The decoded text, or `None` if the data has an odd length or a code Hanzi mode cannot encode
+pub fn gb2312_decode(data: &[u8]) -> Option<String> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let mut output = String::with_capacity(data.len());
+
+    for code in data.chunks(2) {
+        let gb2312 = ((code[0] as u16) << 8) | code[1] as u16;
+        let index = GB2312_DECODE_TABLE
+            .binary_search_by_key(&gb2312, |(double_byte, _)| *double_byte)
+            .ok()?;
+
+        output.push(char::from_u32(GB2312_DECODE_TABLE[index].1 as u32)?);
+    }
+
+    Some(output)
+}
+
 // ----------------------------------------------------------
 
 impl QRMode {
@@ -266,7 +420,7 @@ impl QRMode {
     {
         let converted_input: &str = input.into();
 
-        if is_numeric(&converted_input) {
+        if is_numeric(converted_input) {
             let mut digit_buffer: Vec<u8> = Vec::with_capacity(converted_input.len());
             for i in 0..converted_input.len() {
                 // This is to bypass any unnecessary checking
@@ -293,27 +447,12 @@ impl QRMode {
                 data: digit_buffer,
                 version,
                 error_correction_level,
+                fnc1: None,
+                eci: None,
             });
-        } else if is_alphanumeric(&converted_input) {
+        } else if is_alphanumeric(converted_input) {
             // Get the alphanumeric conversion of the data
-            let mut data: Vec<u8> = Vec::with_capacity(converted_input.len());
-            for character in converted_input.bytes() {
-                if character > 47 && character < 58 {
-                    data.push(character - 48);
-                } else if character > 64 && character < 91 {
-                    data.push(character - 55);
-                } else if character == 32 {
-                    data.push(36);
-                } else if character > 35 && character < 38 {
-                    data.push(character + 1);
-                } else if character > 41 && character < 44 {
-                    data.push(character - 2);
-                } else if character > 44 && character < 48 {
-                    data.push(character - 4);
-                } else {
-                    data.push(44);
-                }
-            }
+            let data: Vec<u8> = converted_input.bytes().map(alphanumeric_value).collect();
 
             // Get the version of QR code needed
             let version = {
@@ -333,6 +472,8 @@ impl QRMode {
                 data,
                 version,
                 error_correction_level,
+                fnc1: None,
+                eci: None,
             });
         } else if converted_input.is_ascii() {
             // Get the version of QR code needed
@@ -353,29 +494,226 @@ impl QRMode {
                 data: converted_input.bytes().collect(),
                 version,
                 error_correction_level,
+                fnc1: None,
+                eci: None,
             });
         }
 
-        panic!()
+        // Transcode the text to the most compact character set for byte mode
+        let (charset, data) = Charset::most_compact(converted_input);
+        let mut qr_mode = QRMode::Byte(QrCode {
+            data,
+            version: 0,
+            error_correction_level,
+            fnc1: None,
+            eci: Some(charset),
+        });
+        qr_mode.fit_version();
+
+        // Use Hanzi mode instead if it takes up fewer bits
+        if let Some(data) = gb2312_encode(converted_input) {
+            let mut hanzi_mode = QRMode::Hanzi(QrCode {
+                data,
+                version: 0,
+                error_correction_level,
+                fnc1: None,
+                eci: None,
+            });
+            hanzi_mode.fit_version();
+
+            if hanzi_mode.bit_length(hanzi_mode.version()) < qr_mode.bit_length(qr_mode.version()) {
+                return hanzi_mode;
+            }
+        }
+
+        qr_mode
+    }
+
+    /// Transcodes the input to the given character set and places it in byte mode behind the
+    /// ECI designator of that character set
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The data to be encoded
+    /// * `charset` - The character set the scanner expects
+    /// * `error_correction_level` - The desired error correction level
+    ///
+    /// # Returns
+    ///
+    /// A `QRMode::Byte` representing the encoded data, or `None` if the input cannot be
+    /// represented in the character set
+    pub fn analyze_eci_data(
+        input: &str,
+        charset: Charset,
+        error_correction_level: ErrorCorrectionLevel,
+    ) -> Option<QRMode> {
+        let mut qr_mode = QRMode::Byte(QrCode {
+            data: charset.encode(input)?,
+            version: 0,
+            error_correction_level,
+            fnc1: None,
+            eci: Some(charset),
+        });
+        qr_mode.fit_version();
+
+        Some(qr_mode)
+    }
+
+    /// Analyzes GS1 Application Identifier/value pairs and determines the encoding mode
+    ///
+    /// The element string is encoded behind the FNC1 first position mode indicator, group
+    /// separators become `%` in alphanumeric mode (a literal `%` is doubled) and GS (0x1D) in
+    /// byte mode
+    ///
+    /// # Arguments
+    ///
+    /// * `elements` - Application Identifier/value pairs such as `("01", "09506000134352")`
+    /// * `error_correction_level` - The desired error correction level
+    ///
+    /// # Returns
+    ///
+    /// A `QRMode` variant representing the encoded data, or a `Gs1Error` if the elements are
+    /// invalid or do not fit in a QR code
+    pub fn analyze_gs1_data(
+        elements: &[(&str, &str)],
+        error_correction_level: ErrorCorrectionLevel,
+    ) -> Result<QRMode, Gs1Error> {
+        let element_string = gs1::build_element_string(elements)?;
+
+        let qr_mode = QRMode::analyze_fnc1_data(
+            element_string.as_str(),
+            Fnc1::FirstPosition,
+            error_correction_level,
+        );
+
+        if qr_mode.version() >= MAX_VERSION {
+            return Err(Gs1Error::DataTooLong);
+        }
+
+        Ok(qr_mode)
+    }
+
+    /// Analyzes data marked with an FNC1 mode indicator and determines the encoding mode
+    ///
+    /// Group separators (GS, 0x1D) in the data act as FNC1 field separators. They become `%` in
+    /// alphanumeric mode (a literal `%` is doubled) and stay GS in byte mode. The version is
+    /// chosen with the extra mode indicator bits taken into account
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The data to be encoded
+    /// * `fnc1` - The FNC1 mode indicator to place ahead of the data
+    /// * `error_correction_level` - The desired error correction level
+    ///
+    /// # Returns
+    ///
+    /// A `QRMode` variant representing the encoded data
+    pub fn analyze_fnc1_data(
+        input: &str,
+        fnc1: Fnc1,
+        error_correction_level: ErrorCorrectionLevel,
+    ) -> QRMode {
+        let alphanumeric_form = input.replace('%', "%%").replace(gs1::GROUP_SEPARATOR, "%");
+
+        let mut qr_mode = if is_alphanumeric(&alphanumeric_form) {
+            QRMode::analyze_data(alphanumeric_form.as_str(), error_correction_level)
+        } else {
+            QRMode::analyze_data(input, error_correction_level)
+        };
+
+        qr_mode.set_fnc1(fnc1);
+        qr_mode
     }
 
     // Private getters for easy abstraction
-    fn version(&self) -> usize {
+    fn qr_code(&self) -> &QrCode {
         use QRMode::*;
         match self {
-            Numeric(qr_code) | AlphaNumeric(qr_code) | Byte(qr_code) => qr_code.version,
+            Numeric(qr_code) | AlphaNumeric(qr_code) | Byte(qr_code) | Hanzi(qr_code) => qr_code,
         }
     }
 
+    fn version(&self) -> usize {
+        self.qr_code().version
+    }
+
     fn error_correction_level(&self) -> &ErrorCorrectionLevel {
+        &self.qr_code().error_correction_level
+    }
+
+    fn fnc1(&self) -> Option<Fnc1> {
+        self.qr_code().fnc1
+    }
+
+    fn eci(&self) -> Option<Charset> {
+        self.qr_code().eci
+    }
+
+    fn qr_code_mut(&mut self) -> &mut QrCode {
         use QRMode::*;
         match self {
-            Numeric(qr_code) | AlphaNumeric(qr_code) | Byte(qr_code) => {
-                &qr_code.error_correction_level
-            }
+            Numeric(qr_code) | AlphaNumeric(qr_code) | Byte(qr_code) | Hanzi(qr_code) => qr_code,
         }
     }
 
+    /// Marks the data with an FNC1 mode indicator and grows the version to fit the extra bits
+    fn set_fnc1(&mut self, fnc1: Fnc1) {
+        self.qr_code_mut().fnc1 = Some(fnc1);
+        self.fit_version();
+    }
+
+    /// Increases the version until the encoded data fits in the data codewords
+    fn fit_version(&mut self) {
+        while self.version() < MAX_VERSION
+            && self.bit_length(self.version())
+                > self
+                    .error_correction_level()
+                    .get_num_codewords(self.version())
+                    * BYTE_SIZE
+        {
+            self.qr_code_mut().version += 1;
+        }
+    }
+
+    /// Number of bits in the character count indicator for the given version
+    fn character_count_bits(&self, version: usize) -> usize {
+        let (small, medium, large) = match self {
+            QRMode::Numeric(_) => (10, 12, 14),
+            QRMode::AlphaNumeric(_) => (9, 11, 13),
+            QRMode::Byte(_) => (8, 16, 16),
+            QRMode::Hanzi(_) => (8, 10, 12),
+        };
+
+        if (version + 1) < 10 {
+            small
+        } else if (version + 1) < 27 {
+            medium
+        } else {
+            large
+        }
+    }
+
+    /// Number of bits the encoded data takes up before the terminator and padding
+    fn bit_length(&self, version: usize) -> usize {
+        let eci_bits = self.eci().map_or(0, |charset| charset.bit_length());
+        let fnc1_bits = self.fnc1().map_or(0, |fnc1| fnc1.bit_length());
+
+        let data_bits = match self {
+            QRMode::Numeric(qr_code) => {
+                let remainder_bits = [0, 4, 7];
+                (qr_code.data.len() / 3) * 10 + remainder_bits[qr_code.data.len() % 3]
+            }
+            QRMode::AlphaNumeric(qr_code) => {
+                (qr_code.data.len() / 2) * 11 + (qr_code.data.len() % 2) * 6
+            }
+            QRMode::Byte(qr_code) => qr_code.data.len() * BYTE_SIZE,
+            // Subset indicator followed by 13 bits per character
+            QRMode::Hanzi(qr_code) => 4 + (qr_code.data.len() / 2) * 13,
+        };
+
+        eci_bits + fnc1_bits + 4 + self.character_count_bits(version) + data_bits
+    }
+
     /// Encodes the QR code data into a bitstring
     ///
     /// # Returns
@@ -383,7 +721,47 @@ impl QRMode {
     /// A `BitString` containing the encoded data
     pub fn encode(&mut self) -> BitString {
         let mut bit_string: BitString = BitString::new();
-        let size_of_character_length_bits: usize;
+        let size_of_character_length_bits = self.character_count_bits(self.version());
+
+        // Add the ECI mode indicator and designator ahead of the data
+        if let Some(charset) = self.eci() {
+            bit_string.push_bit(0);
+            bit_string.push_bit(1);
+            bit_string.push_bit(1);
+            bit_string.push_bit(1);
+
+            let designator = charset.designator();
+            let designator_bits = charset.bit_length() - 4;
+
+            // Designators above 127 are prefixed with 10 (16 bits) or 110 (24 bits)
+            let prefix: u32 = match designator_bits {
+                8 => 0,
+                16 => 0b10 << 14,
+                _ => 0b110 << 21,
+            };
+
+            for i in (0..designator_bits).rev() {
+                bit_string.push_bit((prefix | designator) & (1 << i));
+            }
+        }
+
+        // Add the FNC1 mode indicator ahead of the data
+        match self.fnc1() {
+            Some(Fnc1::FirstPosition) => {
+                bit_string.push_bit(0);
+                bit_string.push_bit(1);
+                bit_string.push_bit(0);
+                bit_string.push_bit(1);
+            }
+            Some(Fnc1::SecondPosition(application_indicator)) => {
+                bit_string.push_bit(1);
+                bit_string.push_bit(0);
+                bit_string.push_bit(0);
+                bit_string.push_bit(1);
+                bit_string.push_byte(application_indicator);
+            }
+            None => {}
+        }
 
         // Perform the mode dependent encoding
         match self {
@@ -393,19 +771,6 @@ impl QRMode {
                 bit_string.push_bit(0);
                 bit_string.push_bit(1);
 
-                size_of_character_length_bits = {
-                    let out: usize;
-                    if (nqr.version + 1) < 10 {
-                        out = 10;
-                    } else if (nqr.version + 1) > 9 && (nqr.version + 1) < 27 {
-                        out = 12;
-                    } else {
-                        out = 14;
-                    }
-
-                    out
-                };
-
                 // Encode the character count
                 for i in (0..size_of_character_length_bits).rev() {
                     bit_string.push_bit((nqr.data.len() & (1 << i)) as i32);
@@ -463,19 +828,6 @@ impl QRMode {
                 bit_string.push_bit(1);
                 bit_string.push_bit(0);
 
-                size_of_character_length_bits = {
-                    let out: usize;
-                    if (anqr.version + 1) < 10 {
-                        out = 9;
-                    } else if (anqr.version + 1) > 9 && (anqr.version + 1) < 27 {
-                        out = 11;
-                    } else {
-                        out = 13;
-                    }
-
-                    out
-                };
-
                 // Encode the character count
                 for i in (0..size_of_character_length_bits).rev() {
                     bit_string.push_bit((anqr.data.len() & (1 << i)) as i32);
@@ -513,17 +865,6 @@ impl QRMode {
                 bit_string.push_bit(0);
                 bit_string.push_bit(0);
 
-                size_of_character_length_bits = {
-                    let out: usize;
-                    if (bqr.version + 1) < 10 {
-                        out = 8;
-                    } else {
-                        out = 16;
-                    }
-
-                    out
-                };
-
                 // Encode the character count
                 for i in (0..size_of_character_length_bits).rev() {
                     bit_string.push_bit((bqr.data.len() & (1 << i)) as i32);
@@ -534,6 +875,41 @@ impl QRMode {
                     bit_string.push_byte(*data);
                 }
             }
+            QRMode::Hanzi(hqr) => {
+                // Adding the mode indicator
+                bit_string.push_bit(1);
+                bit_string.push_bit(1);
+                bit_string.push_bit(0);
+                bit_string.push_bit(1);
+
+                // Subset indicator for GB 2312
+                bit_string.push_bit(0);
+                bit_string.push_bit(0);
+                bit_string.push_bit(0);
+                bit_string.push_bit(1);
+
+                // Encode the character count
+                for i in (0..size_of_character_length_bits).rev() {
+                    bit_string.push_bit(((hqr.data.len() / 2) & (1 << i)) as i32);
+                }
+
+                // Compact every double byte into 13 bits
+                for character in hqr.data.chunks(2) {
+                    let gb2312 = ((character[0] as u16) << 8) | character[1] as u16;
+
+                    let subtracted = if gb2312 < 0xB0A1 {
+                        gb2312 - 0xA1A1
+                    } else {
+                        gb2312 - 0xA6A1
+                    };
+
+                    let encoded = (subtracted >> 8) * 0x60 + (subtracted & 0xFF);
+
+                    for i in (0..13).rev() {
+                        bit_string.push_bit(encoded & (1 << i));
+                    }
+                }
+            }
         }
 
         // The rest is the mode independed encoding
@@ -544,9 +920,7 @@ impl QRMode {
 
         // Add terminator 0s if necessary
         {
-            let total_bits = bit_string.len() - 4 - (size_of_character_length_bits);
-
-            let bit_difference = required_number_of_bits - total_bits;
+            let bit_difference = required_number_of_bits.saturating_sub(bit_string.len());
 
             for _ in 0..bit_difference.min(4) {
                 bit_string.push_bit(0);
@@ -554,7 +928,7 @@ impl QRMode {
         }
 
         // Make sure the bitstring is a multiple of 8
-        while bit_string.len() % 8 != 0 {
+        while !bit_string.len().is_multiple_of(8) {
             bit_string.push_bit(0);
         }
 
@@ -586,7 +960,7 @@ impl QRMode {
             bit_string.push_bit(1);
         }
 
-        return bit_string;
+        bit_string
     }
 
     /// Generates error correction codes for the QR code data
@@ -620,7 +994,7 @@ impl QRMode {
                 index += 1;
             }
 
-            data.push(Vec::from(block.clone()));
+            data.push(block.clone());
             message_polynomials.push(Polynomial::from_integer_notation(block));
         }
 
@@ -631,19 +1005,20 @@ impl QRMode {
                 index += 1;
             }
 
-            data.push(Vec::from(block.clone()));
+            data.push(block.clone());
             message_polynomials.push(Polynomial::from_integer_notation(block));
         }
 
         for message_polynomial in message_polynomials {
+            let num_error_correction_codewords = self
+                .error_correction_level()
+                .get_num_error_correction_codewords(self.version());
+
             // Create a generator polynomial based on the number of blocks needed
             let mut generator_polynomial = {
                 let mut poly = Polynomial::from_exponent_notation(vec![0, 0]);
 
-                for i in 1..self
-                    .error_correction_level()
-                    .get_num_error_correction_codewords(self.version())
-                {
+                for i in 1..num_error_correction_codewords {
                     poly =
                         poly.multiply(&mut Polynomial::from_exponent_notation(vec![0, i as i32]));
                 }
@@ -651,8 +1026,16 @@ impl QRMode {
                 poly
             };
 
-            // Perform the long division on the message polynomial with the generator polynomial
-            let mut current_message = message_polynomial.clone();
+            // Perform the long division on the message polynomial with the generator polynomial,
+            // shifted so the trailing coefficients of the remainder are kept even when they are 0
+            let mut current_message = Polynomial::from_integer_notation(
+                message_polynomial
+                    .clone()
+                    .get_as_integer_vec()
+                    .into_iter()
+                    .chain(std::iter::repeat_n(0, num_error_correction_codewords))
+                    .collect(),
+            );
             let mut inter_poly;
             let mut step = 0;
             while step < message_polynomial.len() {
@@ -672,7 +1055,9 @@ impl QRMode {
                 step += 1;
             }
 
-            while current_message.len() < message_polynomial.len() {
+            // Leading zeros of the remainder are dropped by the division but still take up a
+            // codeword each
+            while current_message.len() < num_error_correction_codewords {
                 current_message = current_message.prepend(0);
             }
 
@@ -742,6 +1127,20 @@ impl QRMode {
     ///
     /// A `BitMap` representing the complete QR code
     pub fn create_bit_map(&self, bits: BitString) -> BitMap {
+        self.create_masked_bit_map(bits, 0)
+    }
+
+    /// Creates the final QR code bitmap with a specific data mask
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The encoded bitstring to place in the bitmap
+    /// * `mask` - The data mask pattern (0 - 7)
+    ///
+    /// # Returns
+    ///
+    /// A `BitMap` representing the complete QR code
+    pub fn create_masked_bit_map(&self, bits: BitString, mask: u8) -> BitMap {
         let size = 21 + (4 * (self.version()));
 
         let mut bit_map = BitMap::new(size);
@@ -752,10 +1151,15 @@ impl QRMode {
         create_alignment_patterns(&mut bit_map, &mut reservations);
         create_timing_patterns(&mut bit_map, &mut reservations);
         create_dark_module(&mut bit_map, &mut reservations);
-        reserve_format_information_areas(&mut reservations);
+        reserve_format_information_areas(&mut bit_map, &mut reservations);
         place_data_bits(&mut bit_map, &reservations, &bits);
-        mask_data(&mut bit_map, &reservations);
-        add_format_information(&mut bit_map, self.error_correction_level(), self.version());
+        mask_data(&mut bit_map, &reservations, mask);
+        add_format_information(
+            &mut bit_map,
+            self.error_correction_level(),
+            self.version(),
+            mask,
+        );
 
         bit_map
     }
@@ -765,9 +1169,9 @@ fn add_format_information(
     bit_map: &mut BitMap,
     error_correction_level: &ErrorCorrectionLevel,
     version: usize,
+    mask: u8,
 ) {
-    // HACK: temparory mask variable while still testing with only mask 0
-    let mask = 0;
+    let mask = mask as u32 & 0b111;
 
     // Put the bits into the bitmap
     let mut index = 0;
@@ -835,13 +1239,13 @@ fn add_format_information(
 
     // Set bits 6, 7, and 8
     bit_map.set(8, 7, bit_6);
-    bit_map.set(bit_map.size() - 6, 8, bit_6);
+    bit_map.set(bit_map.size() - 7, 8, bit_6);
 
     bit_map.set(8, 8, bit_7);
-    bit_map.set(8, bit_map.size() - 7, bit_7);
+    bit_map.set(8, bit_map.size() - 8, bit_7);
 
     bit_map.set(7, 8, bit_8);
-    bit_map.set(8, bit_map.size() - 6, bit_8);
+    bit_map.set(8, bit_map.size() - 7, bit_8);
 
     for i in 9..=14 {
         let bit = bits & (0x4000 >> index);
@@ -851,141 +1255,143 @@ fn add_format_information(
     }
 }
 
-// TODO: Add support for other data masking as well
-fn mask_data(bit_map: &mut BitMap, reservations: &BitMap) {
-    // Doing data masking number 0
+// TODO: Pick the mask with the lowest penalty score
+fn mask_data(bit_map: &mut BitMap, reservations: &BitMap, mask: u8) {
     for row in 0..bit_map.size() {
         for column in 0..bit_map.size() {
-            if (row + column) % 2 == 0 {
-                if reservations.get(row, column) == Bit::Zero {
-                    bit_map.invert_bit(row, column);
-                }
+            if mask_applies(mask, row, column) && reservations.get(row, column) == Bit::Zero {
+                bit_map.invert_bit(row, column);
             }
         }
     }
 }
 
-fn place_data_bits(bit_map: &mut BitMap, reservations: &BitMap, bits: &BitString) {
-    enum Direction {
-        Up,
-        Down,
+/// Checks whether a data mask pattern inverts a module
+///
+/// # Arguments
+///
+/// * `mask` - The data mask pattern (0 - 7)
+/// * `row` - Row index of the module
+/// * `column` - Column index of the module
+pub(crate) fn mask_applies(mask: u8, row: usize, column: usize) -> bool {
+    let (i, j) = (row, column);
+
+    match mask & 0b111 {
+        0 => (i + j) % 2 == 0,
+        1 => i % 2 == 0,
+        2 => j % 3 == 0,
+        3 => (i + j) % 3 == 0,
+        4 => (i / 2 + j / 3) % 2 == 0,
+        5 => (i * j) % 2 + (i * j) % 3 == 0,
+        6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+        _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
     }
+}
 
-    impl Direction {
-        fn toggle(&mut self) {
-            match self {
-                Direction::Up => {
-                    *self = Direction::Down;
-                }
-                Direction::Down => {
-                    *self = Direction::Up;
-                }
-            }
-        }
+fn place_data_bits(bit_map: &mut BitMap, reservations: &BitMap, bits: &BitString) {
+    for (index, (row, column)) in data_module_order(reservations).into_iter().enumerate() {
+        bit_map.set(row, column, bits.get_bit(index).unwrap());
     }
+}
 
-    // Place bits up in a zig zag pattern
-    fn place_bits(
-        bit_map: &mut BitMap,
-        reservations: &BitMap,
-        bits: &BitString,
-        index: &mut usize,
-        x_pos: usize,
-        direction: &Direction,
-    ) {
-        let mut y_pos = match direction {
-            Direction::Up => bit_map.size() - 1,
-            Direction::Down => 0,
-        };
-
-        loop {
-            // if *index >= bits.len() {
-            //     break;
-            // }
+/// Lists the data modules of a placed QR code in the order the data bits are placed in them
+///
+/// # Arguments
+///
+/// * `bit_map` - The QR code with the roles of its modules
+pub(crate) fn data_modules(bit_map: &BitMap) -> Vec<(usize, usize)> {
+    let mut reservations = BitMap::new(bit_map.size());
 
-            if reservations.get(y_pos, x_pos) == Bit::Zero {
-                bit_map.set(y_pos, x_pos, bits.get_bit(*index).unwrap());
-                *index += 1;
+    for row in 0..bit_map.size() {
+        for column in 0..bit_map.size() {
+            if bit_map.role(row, column).is_function_pattern() {
+                reservations.set(row, column, Bit::One);
             }
+        }
+    }
 
-            // if *index >= bits.len() {
-            //     break;
-            // }
-
-            if reservations.get(y_pos, x_pos - 1) == Bit::Zero {
-                bit_map.set(y_pos, x_pos - 1, bits.get_bit(*index).unwrap());
-                *index += 1;
-            }
+    data_module_order(&reservations)
+}
 
-            if y_pos
-                == match direction {
-                    Direction::Up => 0,
-                    Direction::Down => bit_map.size() - 1,
+/// Lists the modules that are not reserved in the order the data bits are placed in them
+///
+/// The bits go up and down in a zig zag through pairs of columns, from the bottom right and
+/// skipping the vertical timing pattern
+///
+/// # Arguments
+///
+/// * `reservations` - Bitmap with the function pattern modules set
+///
+/// # Returns
+///
+/// The row and column of every data module, one per bit of the codewords
+fn data_module_order(reservations: &BitMap) -> Vec<(usize, usize)> {
+    let size = reservations.size();
+    let mut order = Vec::new();
+    let mut upwards = true;
+    let mut column = size - 1;
+
+    loop {
+        for step in 0..size {
+            let row = if upwards { size - 1 - step } else { step };
+
+            for column in [column, column - 1] {
+                if reservations.get(row, column) == Bit::Zero {
+                    order.push((row, column));
                 }
-            {
-                break;
             }
-
-            y_pos = match direction {
-                Direction::Up => y_pos - 1,
-                Direction::Down => y_pos + 1,
-            };
         }
-    }
-
-    let mut index = 0;
-    let mut x_pos = bit_map.size() - 1;
-    let mut current_direction = Direction::Up;
-
-    while x_pos > 0 {
-        place_bits(
-            bit_map,
-            reservations,
-            bits,
-            &mut index,
-            x_pos,
-            &current_direction,
-        );
 
-        if x_pos < 2 {
+        if column < 2 {
             break;
         }
 
-        x_pos -= 2;
+        column -= 2;
 
-        if x_pos == 6 {
-            x_pos -= 1;
+        if column == 6 {
+            column -= 1;
         }
 
-        current_direction.toggle();
+        upwards = !upwards;
     }
+
+    order
 }
 
-fn reserve_format_information_areas(reservations: &mut BitMap) {
+fn reserve_format_information_areas(bit_map: &mut BitMap, reservations: &mut BitMap) {
     let version = ((reservations.size() - 21) / 4) + 1;
+    let size = reservations.size();
 
-    if version >= 6 {
+    // Only symbols from version 7 carry version information
+    if version >= 7 {
         for i in 0..=5 {
-            reservations.set(i, reservations.size() - 11, 1);
-            reservations.set(i, reservations.size() - 10, 1);
-            reservations.set(i, reservations.size() - 9, 1);
+            for j in size - 11..=size - 9 {
+                reservations.set(i, j, 1);
+                reservations.set(j, i, 1);
 
-            reservations.set(reservations.size() - 11, i, 1);
-            reservations.set(reservations.size() - 10, i, 1);
-            reservations.set(reservations.size() - 9, i, 1);
+                bit_map.set_role(i, j, ModuleRole::VersionInformation);
+                bit_map.set_role(j, i, ModuleRole::VersionInformation);
+            }
         }
     }
 
     for i in 0..=8 {
-        reservations.set(i, 8, 1);
-        reservations.set(8, i, 1);
-        reservations.set(reservations.size() - i, 8, 1);
-        reservations.set(8, reservations.size() - i, 1);
+        let modules = [(i, 8), (8, i), (size - i, 8), (8, size - i)];
+
+        for (row, column) in modules {
+            reservations.set(row, column, 1);
+
+            // The timing patterns and the dark module cross the format information areas
+            if bit_map.role(row, column) == ModuleRole::Data {
+                bit_map.set_role(row, column, ModuleRole::FormatInformation);
+            }
+        }
     }
 }
 
 fn create_dark_module(bit_map: &mut BitMap, reservations: &mut BitMap) {
     bit_map.set(bit_map.size() - 8, 8, Bit::One);
+    bit_map.set_role(bit_map.size() - 8, 8, ModuleRole::DarkModule);
     reservations.set(reservations.size() - 8, 8, Bit::One);
 }
 
@@ -996,6 +1402,13 @@ fn create_timing_patterns(bit_map: &mut BitMap, reservations: &mut BitMap) {
             bit_map.set(6, i, Bit::One);
         }
 
+        // Alignment patterns on the timing patterns keep their role
+        for (row, column) in [(i, 6), (6, i)] {
+            if bit_map.role(row, column) == ModuleRole::Data {
+                bit_map.set_role(row, column, ModuleRole::Timing);
+            }
+        }
+
         reservations.set(i, 6, Bit::One);
         reservations.set(6, i, Bit::One);
     }
@@ -1025,7 +1438,7 @@ fn create_alignment_patterns(bit_map: &mut BitMap, reservations: &mut BitMap) {
         // The center dot
         bit_map.set(i, j, Bit::One);
 
-        for x in -2..=2 as isize {
+        for x in -2..=2_isize {
             bit_map.set(i - 2, (j as isize + x) as usize, 1);
             bit_map.set(i + 2, (j as isize + x) as usize, 1);
             bit_map.set((i as isize + x) as usize, j + 2, 1);
@@ -1033,13 +1446,15 @@ fn create_alignment_patterns(bit_map: &mut BitMap, reservations: &mut BitMap) {
         }
 
         // Reserve the alignment patterns
-        for reservation_x in -2..=2 as isize {
-            for reservation_y in -2..=2 as isize {
-                reservations.set(
+        for reservation_x in -2..=2_isize {
+            for reservation_y in -2..=2_isize {
+                let (row, column) = (
                     (reservation_x + i as isize) as usize,
                     (reservation_y + j as isize) as usize,
-                    Bit::One,
                 );
+
+                reservations.set(row, column, Bit::One);
+                bit_map.set_role(row, column, ModuleRole::Alignment);
             }
         }
     }
@@ -1049,10 +1464,7 @@ fn create_alignment_patterns(bit_map: &mut BitMap, reservations: &mut BitMap) {
     for x in 0..coords.len() {
         for y in 0..coords.len() {
             // Skip the coordinate if it is an invalid placement
-            if (x == 0 && y == 0)
-                || (x == 0 && y == coords.len() - 1)
-                || (x == coords.len() - 1 && y == 0)
-            {
+            if (x == 0 && (y == 0 || y == coords.len() - 1)) || (x == coords.len() - 1 && y == 0) {
                 continue;
             }
 
@@ -1085,16 +1497,31 @@ fn create_finder_patterns(bit_map: &mut BitMap, reservations: &mut BitMap) {
             }
         }
 
+        for x in 0..7 {
+            for y in 0..7 {
+                let role = if (2..5).contains(&x) && (2..5).contains(&y) {
+                    ModuleRole::FinderInner
+                } else {
+                    ModuleRole::FinderOuter
+                };
+                bit_map.set_role(i + x, j + y, role);
+            }
+        }
+
         let (i_off, j_off) = separator_offset;
 
         // Reserve the place on the bitmap with the separators as well
         for reservation_x in i_off..=(i_off + 7) {
             for reservation_y in j_off..=(j_off + 7) {
-                reservations.set(
+                let (row, column) = (
                     (reservation_x + i as isize) as usize,
                     (reservation_y + j as isize) as usize,
-                    Bit::One,
                 );
+
+                reservations.set(row, column, Bit::One);
+                if bit_map.role(row, column) == ModuleRole::Data {
+                    bit_map.set_role(row, column, ModuleRole::Separator);
+                }
             }
         }
     }
@@ -1108,6 +1535,134 @@ fn create_finder_patterns(bit_map: &mut BitMap, reservations: &mut BitMap) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_gs1_fnc1_first_position() {
+        let mut qr_mode =
+            QRMode::analyze_gs1_data(&[("01", "09506000134352")], ErrorCorrectionLevel::L).unwrap();
+        assert!(matches!(qr_mode, QRMode::Numeric(_)));
+
+        // FNC1 first position, numeric mode and a character count of 16
+        let bits = format!("{}", qr_mode.encode());
+        assert!(bits.starts_with(concat!("0101", "0001", "0000010000")));
+
+        let qr_mode = QRMode::analyze_gs1_data(
+            &[("10", "ABC%1"), ("17", "201225")],
+            ErrorCorrectionLevel::L,
+        )
+        .unwrap();
+        assert_eq!(
+            qr_mode,
+            QRMode::AlphaNumeric(QrCode {
+                data: "10ABC%%1%17201225"
+                    .bytes()
+                    .map(alphanumeric_value)
+                    .collect(),
+                version: 0,
+                error_correction_level: ErrorCorrectionLevel::L,
+                fnc1: Some(Fnc1::FirstPosition),
+                eci: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_fnc1_second_position() {
+        assert_eq!(
+            Fnc1::application_indicator("37"),
+            Some(Fnc1::SecondPosition(37))
+        );
+        assert_eq!(
+            Fnc1::application_indicator("a"),
+            Some(Fnc1::SecondPosition(197))
+        );
+        assert_eq!(Fnc1::application_indicator("1"), None);
+
+        let mut qr_mode =
+            QRMode::analyze_fnc1_data("AB%C", Fnc1::SecondPosition(37), ErrorCorrectionLevel::L);
+
+        // FNC1 second position, application indicator 37, alphanumeric mode and a count of 5
+        let bits = format!("{}", qr_mode.encode());
+        assert!(bits.starts_with(concat!("1001", "00100101", "0010", "000000101")));
+
+        // 25 alphanumeric characters fit version 1-L, but not with the extra 12 bits
+        let qr_mode = QRMode::analyze_fnc1_data(
+            "ABCDEFGHIJKLMNOPQRSTUVWXY",
+            Fnc1::SecondPosition(37),
+            ErrorCorrectionLevel::L,
+        );
+        assert_eq!(qr_mode.version(), 1);
+    }
+
+    #[test]
+    fn test_hanzi_mode() {
+        let mut qr_mode = QRMode::analyze_data("中文", ErrorCorrectionLevel::L);
+        assert_eq!(
+            qr_mode,
+            QRMode::Hanzi(QrCode {
+                data: vec![0xD6, 0xD0, 0xCE, 0xC4],
+                version: 0,
+                error_correction_level: ErrorCorrectionLevel::L,
+                fnc1: None,
+                eci: None,
+            })
+        );
+
+        // Hanzi mode, GB 2312 subset, a count of 2, then 0xD6D0 and 0xCEC4 as 13 bit values
+        let bits = format!("{}", qr_mode.encode());
+        assert!(bits.starts_with(concat!(
+            "1101",
+            "0001",
+            "00000010",
+            "1001000101111",
+            "0111100100011"
+        )));
+    }
+
+    #[test]
+    fn test_gb2312_round_trip() {
+        assert_eq!(
+            gb2312_decode(&[0xD6, 0xD0, 0xCE, 0xC4]).as_deref(),
+            Some("中文")
+        );
+
+        // Every character Hanzi mode can encode decodes back to itself
+        let text: String = GB2312_TABLE
+            .iter()
+            .filter_map(|(unicode, _)| char::from_u32(*unicode as u32))
+            .collect();
+        let data = gb2312_encode(&text).unwrap();
+        assert_eq!(gb2312_decode(&data), Some(text));
+
+        // Odd lengths and codes outside the Hanzi mode rows are rejected
+        assert_eq!(gb2312_decode(&[0xD6]), None);
+        assert_eq!(gb2312_decode(&[0xAB, 0xA1]), None);
+    }
+
+    #[test]
+    fn test_eci_byte_mode() {
+        let mut qr_mode = QRMode::analyze_data("Grüße", ErrorCorrectionLevel::L);
+        assert_eq!(
+            qr_mode,
+            QRMode::Byte(QrCode {
+                data: vec![0x47, 0x72, 0xFC, 0xDF, 0x65],
+                version: 0,
+                error_correction_level: ErrorCorrectionLevel::L,
+                fnc1: None,
+                eci: Some(Charset::Iso8859_1),
+            })
+        );
+
+        // ECI designator 3, byte mode and a count of 5
+        let bits = format!("{}", qr_mode.encode());
+        assert!(bits.starts_with(concat!("0111", "00000011", "0100", "00000101")));
+
+        // 17 bytes fit version 1-L, but not with the extra 12 bits of the ECI header
+        let qr_mode =
+            QRMode::analyze_eci_data("ABCDEFGHIJKLMNOPQ", Charset::Utf8, ErrorCorrectionLevel::L)
+                .unwrap();
+        assert_eq!(qr_mode.version(), 1);
+    }
+
     #[test]
     fn test_qr_modes() {
         let qr_mode = QRMode::analyze_data("A113", ErrorCorrectionLevel::L);
@@ -1116,8 +1671,202 @@ mod tests {
             QRMode::AlphaNumeric(QrCode {
                 data: vec![10, 1, 1, 3],
                 version: 0,
-                error_correction_level: ErrorCorrectionLevel::L
+                error_correction_level: ErrorCorrectionLevel::L,
+                fnc1: None,
+                eci: None,
             })
         );
     }
+
+    #[test]
+    fn test_module_roles() {
+        // Version 2 has one alignment pattern centred on (18, 18)
+        let mut qr_mode = QRMode::analyze_data("A".repeat(30).as_str(), ErrorCorrectionLevel::L);
+        let bits = qr_mode.encode();
+        let data = qr_mode.generate_error_correction(bits);
+        let bit_map = qr_mode.create_bit_map(qr_mode.structure_codewords(data));
+        let size = bit_map.size();
+        assert_eq!(size, 25);
+
+        assert_eq!(bit_map.role(0, 0), ModuleRole::FinderOuter);
+        assert_eq!(bit_map.role(1, size - 2), ModuleRole::FinderOuter);
+        assert_eq!(bit_map.role(size - 4, 3), ModuleRole::FinderInner);
+        assert_eq!(bit_map.role(7, 7), ModuleRole::Separator);
+        assert_eq!(bit_map.role(6, 10), ModuleRole::Timing);
+        assert_eq!(bit_map.role(6, 8), ModuleRole::Timing);
+        assert_eq!(bit_map.role(8, 2), ModuleRole::FormatInformation);
+        assert_eq!(bit_map.role(size - 8, 8), ModuleRole::DarkModule);
+        assert_eq!(bit_map.role(16, 20), ModuleRole::Alignment);
+        assert_eq!(bit_map.role(12, 12), ModuleRole::Data);
+    }
+
+    #[test]
+    fn test_version_6_data_modules() {
+        // Version 6 carries no version information, so only the function patterns are reserved
+        let size = 41;
+        let mut bit_map = BitMap::new(size);
+        let mut reservations = BitMap::new(size);
+
+        create_finder_patterns(&mut bit_map, &mut reservations);
+        create_alignment_patterns(&mut bit_map, &mut reservations);
+        create_timing_patterns(&mut bit_map, &mut reservations);
+        create_dark_module(&mut bit_map, &mut reservations);
+        reserve_format_information_areas(&mut bit_map, &mut reservations);
+
+        // 172 codewords and 7 remainder bits
+        let order = data_module_order(&reservations);
+        assert_eq!(order.len(), 172 * 8 + 7);
+
+        // The zig zag starts upwards from the bottom right corner
+        assert_eq!(order[..4], [(40, 40), (40, 39), (39, 40), (39, 39)]);
+
+        // The version information areas of larger symbols hold data here
+        assert!(order.contains(&(0, 31)) && order.contains(&(31, 0)));
+    }
+
+    #[test]
+    fn test_error_correction_codewords() {
+        let mut qr_mode = QRMode::analyze_data("HELLO WORLD", ErrorCorrectionLevel::M);
+        let bits = qr_mode.encode();
+        let (data, error_correction) = qr_mode.generate_error_correction(bits);
+
+        assert_eq!(
+            data,
+            vec![vec![
+                32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17
+            ]]
+        );
+        assert_eq!(
+            error_correction,
+            vec![vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]]
+        );
+
+        // Every block has exactly the error correction codewords of the version, even when the
+        // remainder starts or ends with zeros
+        let text: String = (0..200)
+            .map(|i| (b'a' + (i * 7 % 26) as u8) as char)
+            .collect();
+        for level in [
+            ErrorCorrectionLevel::L,
+            ErrorCorrectionLevel::M,
+            ErrorCorrectionLevel::Q,
+            ErrorCorrectionLevel::H,
+        ] {
+            let mut qr_mode = QRMode::analyze_data(text.as_str(), level);
+            let bits = qr_mode.encode();
+            let expected = qr_mode
+                .error_correction_level()
+                .get_num_error_correction_codewords(qr_mode.version());
+
+            let (_, error_correction) = qr_mode.generate_error_correction(bits);
+            assert!(error_correction.iter().all(|block| block.len() == expected));
+        }
+    }
+
+    #[test]
+    fn test_multi_block_codewords() {
+        // Version 5-Q splits the data into two blocks of 15 and two blocks of 16 codewords, and
+        // the error correction of the last block starts with a zero codeword
+        let mut qr_mode = QRMode::analyze_data(
+            "THE QUICK BROWN FOX JUMPS OVER THE LAZY DOG 0123456789 $%-./: PACK MY BOX WITH 136",
+            ErrorCorrectionLevel::Q,
+        );
+        assert_eq!(qr_mode.version(), 4);
+
+        let bits = qr_mode.encode();
+        let (data, error_correction) = qr_mode.generate_error_correction(bits);
+
+        assert_eq!(
+            data,
+            vec![
+                vec![34, 149, 42, 83, 82, 193, 155, 58, 132, 21, 22, 33, 122, 187, 190],
+                vec![45, 213, 251, 209, 8, 174, 164, 115, 139, 11, 205, 39, 151, 15, 38],
+                vec![21, 232, 0, 66, 232, 185, 34, 165, 199, 60, 237, 127, 27, 248, 35, 122],
+                vec![48, 205, 88, 121, 3, 223, 27, 101, 74, 178, 168, 141, 0, 236, 17, 236],
+            ]
+        );
+        assert_eq!(
+            error_correction,
+            vec![
+                vec![27, 97, 178, 166, 76, 61, 50, 156, 23, 28, 23, 90, 13, 64, 155, 154, 8, 150],
+                vec![
+                    68, 123, 28, 166, 192, 107, 227, 84, 12, 149, 224, 131, 253, 46, 197, 231, 123,
+                    158
+                ],
+                vec![
+                    57, 194, 152, 181, 84, 113, 126, 98, 237, 121, 68, 233, 236, 130, 188, 28, 105,
+                    203
+                ],
+                vec![
+                    0, 71, 100, 214, 199, 51, 153, 146, 85, 108, 201, 105, 230, 196, 221, 210, 7,
+                    11
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_information_masks() {
+        // Format information of level M for every mask, from ISO/IEC 18004 annex C
+        let expected = [
+            0b101010000010010,
+            0b101000100100101,
+            0b101111001111100,
+            0b101101101001011,
+            0b100010111111001,
+            0b100000011001110,
+            0b100111110010111,
+            0b100101010100000,
+        ];
+
+        for (mask, format) in expected.into_iter().enumerate() {
+            let mut qr_mode = QRMode::analyze_data("HELLO WORLD", ErrorCorrectionLevel::M);
+            let bits = qr_mode.encode();
+            let blocks = qr_mode.generate_error_correction(bits);
+            let bit_map =
+                qr_mode.create_masked_bit_map(qr_mode.structure_codewords(blocks), mask as u8);
+            let size = bit_map.size();
+
+            // The copy around the top left finder and the one split over the other two
+            let top_left = (0..6)
+                .map(|i| (8, i))
+                .chain([(8, 7), (8, 8), (7, 8)])
+                .chain((0..6).rev().map(|i| (i, 8)));
+            let split = (0..7)
+                .map(|i| (size - 1 - i, 8))
+                .chain((0..8).map(|i| (8, size - 8 + i)));
+
+            for modules in [top_left.collect::<Vec<_>>(), split.collect()] {
+                let read = modules.iter().fold(0u32, |bits, &(row, column)| {
+                    (bits << 1) | (bit_map.get(row, column) == Bit::One) as u32
+                });
+                assert_eq!(read, format, "mask {}", mask);
+            }
+        }
+    }
+
+    #[test]
+    fn test_alphanumeric_z() {
+        // 'Z' is the last capital letter of the alphanumeric character set
+        assert!(is_alphanumeric("XYZ"));
+
+        match QRMode::analyze_data("ZA", ErrorCorrectionLevel::L) {
+            QRMode::AlphaNumeric(qr_code) => assert_eq!(qr_code.data, vec![35, 10]),
+            qr_mode => panic!("{:?}", qr_mode),
+        }
+    }
+
+    #[test]
+    fn test_alphanumeric_symbols() {
+        // '*', '+' and '-' follow each other in the alphanumeric character set
+        match QRMode::analyze_data("*+-", ErrorCorrectionLevel::L) {
+            QRMode::AlphaNumeric(qr_code) => assert_eq!(qr_code.data, vec![39, 40, 41]),
+            qr_mode => panic!("{:?}", qr_mode),
+        }
+
+        match QRMode::analyze_gs1_data(&[("21", "A+1")], ErrorCorrectionLevel::L).unwrap() {
+            QRMode::AlphaNumeric(qr_code) => assert_eq!(qr_code.data, vec![2, 1, 10, 40, 1]),
+            qr_mode => panic!("{:?}", qr_mode),
+        }
+    }
 }
@@ -0,0 +1,381 @@
+//! # QR Code Pipeline
+//!
+//! Type-state wrappers around the QR code generation stages. Every stage consumes the previous
+//! one and returns a distinct type carrying its data and metadata, so the stages can only be
+//! run in order and the data of one QR code can never be handed to another.
+//!
+//! `Analyzed` → `Encoded` → `Protected` → `Interleaved` → `Placed`
+//!
+//! ```rust,ignore
+//! let placed = Analyzed::new("HELLO WORLD", ErrorCorrectionLevel::M)
+//!     .encode()
+//!     .protect()
+//!     .interleave()
+//!     .place();
+//! let bit_map = placed.into_bit_map();
+//! ```
+
+use super::*;
+
+/// Encoding mode chosen for the data
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum EncodingMode {
+    Numeric,
+    AlphaNumeric,
+    Byte,
+    Hanzi,
+}
+
+/// Data that has been analyzed, with its encoding mode and version chosen
+#[derive(Debug)]
+pub struct Analyzed {
+    qr_mode: QRMode,
+}
+
+/// Data codewords of the QR code including the terminator and pad bytes
+pub struct Encoded {
+    qr_mode: QRMode,
+    bits: BitString,
+}
+
+/// Data codewords split into blocks together with the error correction codewords of each block
+pub struct Protected {
+    qr_mode: QRMode,
+    data_blocks: Vec<Vec<u8>>,
+    error_correction_blocks: Vec<Vec<u8>>,
+}
+
+/// Final codeword sequence with the blocks interleaved and the remainder bits added
+pub struct Interleaved {
+    qr_mode: QRMode,
+    bits: BitString,
+}
+
+/// The finished QR code with every module placed, masked and the format information added
+pub struct Placed {
+    qr_mode: QRMode,
+    bit_map: BitMap,
+}
+
+impl Analyzed {
+    /// Analyzes input data and determines the encoding mode and version
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The data to be encoded
+    /// * `error_correction_level` - The desired error correction level
+    pub fn new(input: &str, error_correction_level: ErrorCorrectionLevel) -> Self {
+        Self {
+            qr_mode: QRMode::analyze_data(input, error_correction_level),
+        }
+    }
+
+    /// Analyzes data transcoded to a specific character set behind its ECI designator
+    ///
+    /// # Returns
+    ///
+    /// `None` if the input cannot be represented in the character set
+    pub fn with_charset(
+        input: &str,
+        charset: Charset,
+        error_correction_level: ErrorCorrectionLevel,
+    ) -> Option<Self> {
+        Some(Self {
+            qr_mode: QRMode::analyze_eci_data(input, charset, error_correction_level)?,
+        })
+    }
+
+    /// Analyzes data marked with an FNC1 mode indicator
+    pub fn with_fnc1(
+        input: &str,
+        fnc1: Fnc1,
+        error_correction_level: ErrorCorrectionLevel,
+    ) -> Self {
+        Self {
+            qr_mode: QRMode::analyze_fnc1_data(input, fnc1, error_correction_level),
+        }
+    }
+
+    /// Analyzes GS1 Application Identifier/value pairs
+    ///
+    /// # Returns
+    ///
+    /// A `Gs1Error` if the elements are invalid or do not fit in a QR code
+    pub fn gs1(
+        elements: &[(&str, &str)],
+        error_correction_level: ErrorCorrectionLevel,
+    ) -> Result<Self, Gs1Error> {
+        Ok(Self {
+            qr_mode: QRMode::analyze_gs1_data(elements, error_correction_level)?,
+        })
+    }
+
+    /// Uses a larger symbol than the data needs
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - The QR code version (1 - 40)
+    ///
+    /// # Returns
+    ///
+    /// `None` if the version is smaller than the chosen one or larger than 40
+    pub fn with_version(mut self, version: usize) -> Option<Self> {
+        if version < self.version() || version > MAX_VERSION {
+            return None;
+        }
+
+        self.qr_mode.qr_code_mut().version = version - 1;
+        Some(self)
+    }
+
+    /// Returns the chosen encoding mode
+    pub fn encoding_mode(&self) -> EncodingMode {
+        match self.qr_mode {
+            QRMode::Numeric(_) => EncodingMode::Numeric,
+            QRMode::AlphaNumeric(_) => EncodingMode::AlphaNumeric,
+            QRMode::Byte(_) => EncodingMode::Byte,
+            QRMode::Hanzi(_) => EncodingMode::Hanzi,
+        }
+    }
+
+    /// Returns the character set designated with an ECI, if any
+    pub fn charset(&self) -> Option<Charset> {
+        self.qr_mode.eci()
+    }
+
+    /// Returns the QR code version (1 - 40)
+    pub fn version(&self) -> usize {
+        self.qr_mode.version() + 1
+    }
+
+    /// Returns the error correction level
+    pub fn error_correction_level(&self) -> ErrorCorrectionLevel {
+        *self.qr_mode.error_correction_level()
+    }
+
+    /// Encodes the data into data codewords
+    pub fn encode(mut self) -> Encoded {
+        let bits = self.qr_mode.encode();
+
+        Encoded {
+            qr_mode: self.qr_mode,
+            bits,
+        }
+    }
+}
+
+impl Encoded {
+    /// Returns the data codewords
+    pub fn codewords(&self) -> &[u8] {
+        self.bits.as_bytes()
+    }
+
+    /// Returns the QR code version (1 - 40)
+    pub fn version(&self) -> usize {
+        self.qr_mode.version() + 1
+    }
+
+    /// Splits the data codewords into blocks and generates their error correction codewords
+    pub fn protect(self) -> Protected {
+        let (data_blocks, error_correction_blocks) =
+            self.qr_mode.generate_error_correction(self.bits);
+
+        Protected {
+            qr_mode: self.qr_mode,
+            data_blocks,
+            error_correction_blocks,
+        }
+    }
+}
+
+impl Protected {
+    /// Returns the data codewords of every block
+    pub fn data_blocks(&self) -> &[Vec<u8>] {
+        &self.data_blocks
+    }
+
+    /// Returns the error correction codewords of every block
+    pub fn error_correction_blocks(&self) -> &[Vec<u8>] {
+        &self.error_correction_blocks
+    }
+
+    /// Returns the QR code version (1 - 40)
+    pub fn version(&self) -> usize {
+        self.qr_mode.version() + 1
+    }
+
+    /// Interleaves the blocks into the final codeword sequence
+    pub fn interleave(self) -> Interleaved {
+        let bits = self
+            .qr_mode
+            .structure_codewords((self.data_blocks, self.error_correction_blocks));
+
+        Interleaved {
+            qr_mode: self.qr_mode,
+            bits,
+        }
+    }
+}
+
+impl Interleaved {
+    /// Returns the interleaved codewords, without the remainder bits
+    pub fn codewords(&self) -> &[u8] {
+        let remainder_bytes = self.remainder_bits().div_ceil(BYTE_SIZE);
+        let bytes = self.bits.as_bytes();
+
+        &bytes[..bytes.len() - remainder_bytes]
+    }
+
+    /// Returns the number of remainder bits placed after the codewords
+    pub fn remainder_bits(&self) -> usize {
+        REQUIRED_REMAINDER_BITS[self.qr_mode.version()]
+    }
+
+    /// Returns the QR code version (1 - 40)
+    pub fn version(&self) -> usize {
+        self.qr_mode.version() + 1
+    }
+
+    /// Places the codewords and function patterns in the symbol
+    pub fn place(self) -> Placed {
+        let bit_map = self.qr_mode.create_bit_map(self.bits);
+
+        Placed {
+            qr_mode: self.qr_mode,
+            bit_map,
+        }
+    }
+}
+
+impl Placed {
+    /// Returns the finished QR code
+    pub fn bit_map(&self) -> &BitMap {
+        &self.bit_map
+    }
+
+    /// Returns the QR code version (1 - 40)
+    pub fn version(&self) -> usize {
+        self.qr_mode.version() + 1
+    }
+
+    /// Returns the error correction level
+    pub fn error_correction_level(&self) -> ErrorCorrectionLevel {
+        *self.qr_mode.error_correction_level()
+    }
+
+    /// Returns the number of erroneous codewords every block can correct
+    ///
+    /// The smallest symbols spend some error correction codewords on misdecode protection, which
+    /// leaves fewer of them for correction
+    pub fn correctable_codewords(&self) -> usize {
+        let level = self.qr_mode.error_correction_level();
+        let version = self.qr_mode.version();
+
+        let misdecode_protection = match (version + 1, level) {
+            (1, ErrorCorrectionLevel::L) => 3,
+            (1, ErrorCorrectionLevel::M) | (2, ErrorCorrectionLevel::L) => 2,
+            (1, ErrorCorrectionLevel::Q) | (3, ErrorCorrectionLevel::L) => 1,
+            _ => 0,
+        };
+
+        (level.get_num_error_correction_codewords(version) - misdecode_protection) / 2
+    }
+
+    /// Counts the codewords of every block that a logo would damage
+    ///
+    /// # Arguments
+    ///
+    /// * `shape` - Area of the logo in the centre of the symbol
+    ///
+    /// # Returns
+    ///
+    /// The number of codewords with at least one module under the logo, per block
+    pub fn logo_damage(&self, shape: &LogoShape) -> Vec<usize> {
+        logo::block_damage(
+            &self.bit_map,
+            self.qr_mode.error_correction_level(),
+            self.qr_mode.version(),
+            shape,
+        )
+    }
+
+    /// Clears the data modules under a logo
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Area of the logo and the share of the correctable codewords it may damage
+    ///
+    /// # Returns
+    ///
+    /// A `LogoError` if the logo covers a function pattern or damages too many codewords of a
+    /// block
+    pub fn with_logo(mut self, options: &LogoOptions) -> Result<Self, LogoError> {
+        let allowed = (self.correctable_codewords() as f64 * options.max_damage.clamp(0.0, 1.0))
+            .floor() as usize;
+        let damaged = self
+            .logo_damage(&options.shape)
+            .into_iter()
+            .max()
+            .unwrap_or(0);
+
+        logo::clear_logo_area(&mut self.bit_map, &options.shape)?;
+
+        if damaged > allowed {
+            return Err(LogoError::TooMuchDamage { damaged, allowed });
+        }
+
+        Ok(self)
+    }
+
+    /// Consumes the stage and returns the finished QR code
+    pub fn into_bit_map(self) -> BitMap {
+        self.bit_map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_stages() {
+        let analyzed = Analyzed::new("HELLO WORLD", ErrorCorrectionLevel::Q);
+        assert_eq!(analyzed.encoding_mode(), EncodingMode::AlphaNumeric);
+        assert_eq!(analyzed.version(), 1);
+
+        let encoded = analyzed.encode();
+        assert_eq!(encoded.codewords().len(), 13);
+
+        let protected = encoded.protect();
+        assert_eq!(protected.data_blocks().len(), 1);
+        assert_eq!(protected.error_correction_blocks()[0].len(), 13);
+
+        let interleaved = protected.interleave();
+        assert_eq!(interleaved.codewords().len(), 26);
+        assert_eq!(interleaved.remainder_bits(), 0);
+
+        let placed = interleaved.place();
+        assert_eq!(placed.bit_map().size(), 21);
+    }
+
+    #[test]
+    fn test_correctable_codewords() {
+        let correctable = |text: &str, level| {
+            Analyzed::new(text, level)
+                .encode()
+                .protect()
+                .interleave()
+                .place()
+                .correctable_codewords()
+        };
+
+        // Misdecode protection codewords of the smallest symbols are not available for correction
+        assert_eq!(correctable("1", ErrorCorrectionLevel::L), 2);
+        assert_eq!(correctable("1", ErrorCorrectionLevel::M), 4);
+        assert_eq!(correctable("1", ErrorCorrectionLevel::Q), 6);
+        assert_eq!(correctable("1", ErrorCorrectionLevel::H), 8);
+        assert_eq!(correctable(&"a".repeat(20), ErrorCorrectionLevel::L), 4);
+        assert_eq!(correctable(&"a".repeat(40), ErrorCorrectionLevel::L), 7);
+        assert_eq!(correctable(&"a".repeat(60), ErrorCorrectionLevel::L), 10);
+    }
+}
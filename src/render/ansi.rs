@@ -0,0 +1,185 @@
+//! # ANSI
+//!
+//! Terminal rendering with explicit colours. Every module is painted with ANSI background (and
+//! foreground, for half blocks) colour escape sequences, so the code scans the same on light and
+//! dark terminal themes.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! print!("{}", qr_code.render_ansi(&AnsiOptions::default()));
+//! ```
+
+use super::Color;
+use crate::bit_utils::bitmap::BitMap;
+use std::io::{self, Write};
+
+const RESET: &str = "\x1b[0m";
+
+/// Colour of ANSI output
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum AnsiColor {
+    /// 24-bit colour, for terminals that support true colour
+    TrueColor(Color),
+    /// Index in the 256 colour palette
+    Indexed(u8),
+}
+
+impl AnsiColor {
+    fn background(&self) -> String {
+        match self {
+            AnsiColor::TrueColor(color) => {
+                format!("\x1b[48;2;{};{};{}m", color.r, color.g, color.b)
+            }
+            AnsiColor::Indexed(index) => format!("\x1b[48;5;{}m", index),
+        }
+    }
+
+    fn foreground(&self) -> String {
+        match self {
+            AnsiColor::TrueColor(color) => {
+                format!("\x1b[38;2;{};{};{}m", color.r, color.g, color.b)
+            }
+            AnsiColor::Indexed(index) => format!("\x1b[38;5;{}m", index),
+        }
+    }
+}
+
+/// Options for rendering with ANSI colours
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct AnsiOptions {
+    /// Colour of the dark modules
+    pub dark: AnsiColor,
+    /// Colour of the light modules and the quiet zone
+    pub light: AnsiColor,
+    /// Width of the quiet zone around the symbol in modules
+    pub quiet_zone: usize,
+    /// Draw two module rows per line with half blocks instead of two spaces per module
+    pub half_block: bool,
+}
+
+impl Default for AnsiOptions {
+    fn default() -> Self {
+        Self {
+            // Black and white of the 256 colour cube
+            dark: AnsiColor::Indexed(16),
+            light: AnsiColor::Indexed(231),
+            quiet_zone: 4,
+            half_block: false,
+        }
+    }
+}
+
+impl BitMap {
+    /// Renders the bitmap as text painted with ANSI colour escape sequences
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Colours, quiet zone and density of the rendering
+    ///
+    /// # Returns
+    ///
+    /// The rendering with the colours reset at the end of every line
+    pub fn render_ansi(&self, options: &AnsiOptions) -> String {
+        let modules = self.size() + 2 * options.quiet_zone;
+        let color = |row: usize, column: usize| {
+            if self.is_dark(row, column, options.quiet_zone) {
+                options.dark
+            } else {
+                options.light
+            }
+        };
+
+        let mut output = String::new();
+
+        if options.half_block {
+            for row in (0..modules).step_by(2) {
+                let mut current: Option<(AnsiColor, AnsiColor)> = None;
+
+                for column in 0..modules {
+                    // The upper half block takes the foreground colour and the lower half the
+                    // background, a missing last row is painted light
+                    let top = color(row, column);
+                    let bottom = if row + 1 < modules {
+                        color(row + 1, column)
+                    } else {
+                        options.light
+                    };
+
+                    if current != Some((top, bottom)) {
+                        output.push_str(&top.foreground());
+                        output.push_str(&bottom.background());
+                        current = Some((top, bottom));
+                    }
+                    output.push('▀');
+                }
+
+                output.push_str(RESET);
+                output.push('\n');
+            }
+        } else {
+            for row in 0..modules {
+                let mut current: Option<AnsiColor> = None;
+
+                for column in 0..modules {
+                    let module = color(row, column);
+
+                    if current != Some(module) {
+                        output.push_str(&module.background());
+                        current = Some(module);
+                    }
+                    output.push_str("  ");
+                }
+
+                output.push_str(RESET);
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    /// Writes the bitmap as text painted with ANSI colour escape sequences
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the text
+    /// * `options` - Colours, quiet zone and density of the rendering
+    pub fn write_ansi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &AnsiOptions,
+    ) -> io::Result<()> {
+        writer.write_all(self.render_ansi(options).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_ansi() {
+        let mut bit_map = BitMap::new(2);
+        bit_map.set(0, 0, 1);
+        bit_map.set(0, 1, 1);
+
+        let mut options = AnsiOptions {
+            dark: AnsiColor::Indexed(16),
+            light: AnsiColor::TrueColor(Color::rgb(255, 255, 0)),
+            quiet_zone: 0,
+            half_block: false,
+        };
+
+        // Escape sequences are only written when the colour changes
+        assert_eq!(
+            bit_map.render_ansi(&options),
+            "\x1b[48;5;16m    \x1b[0m\n\x1b[48;2;255;255;0m    \x1b[0m\n"
+        );
+
+        options.half_block = true;
+        assert_eq!(
+            bit_map.render_ansi(&options),
+            "\x1b[38;5;16m\x1b[48;2;255;255;0m▀▀\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,531 @@
+//! # BMP
+//!
+//! A Windows bitmap writer for 1 and 8-bit palette images and 24 and 32-bit colour images, and a
+//! reader that loads uncompressed bitmaps back into a `BitMap` or a grayscale buffer. Rows are
+//! stored bottom-up and padded to 4 bytes, and the resolution is written in pixels per metre.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! let options = RenderOptions::default();
+//! qr_code.save_bmp("qr_code.bmp", &BmpOptions::default(), &options)?;
+//!
+//! let read = BitMap::read_bmp(File::open("qr_code.bmp")?, &options)?;
+//! ```
+
+use super::netpbm::GrayImage;
+use super::sizing::pixels_per_metre;
+use super::{Color, RenderOptions, Renderer};
+use crate::bit_utils::bitmap::BitMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+// Sizes of the file header and the BITMAPINFOHEADER
+const FILE_HEADER_SIZE: usize = 14;
+const INFO_HEADER_SIZE: usize = 40;
+
+// Compression methods
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+/// Pixel format of the BMP image
+///
+/// The indexed formats only hold the dark and light colours, region colours and gradients need
+/// `Rgb24` or `Rgba32`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum BmpFormat {
+    /// One bit per pixel indexing a two colour palette
+    Indexed1,
+    /// One byte per pixel indexing a two colour palette
+    Indexed8,
+    /// Blue, green and red bytes
+    Rgb24,
+    /// Blue, green, red and an opaque alpha byte
+    Rgba32,
+}
+
+impl BmpFormat {
+    fn bits_per_pixel(&self) -> usize {
+        match self {
+            BmpFormat::Indexed1 => 1,
+            BmpFormat::Indexed8 => 8,
+            BmpFormat::Rgb24 => 24,
+            BmpFormat::Rgba32 => 32,
+        }
+    }
+}
+
+/// Options for writing BMP images
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct BmpOptions {
+    /// Pixel format of the image
+    pub format: BmpFormat,
+    /// Resolution of the image in dots per inch
+    pub dpi: u32,
+}
+
+impl Default for BmpOptions {
+    fn default() -> Self {
+        Self {
+            format: BmpFormat::Indexed1,
+            dpi: 96,
+        }
+    }
+}
+
+/// Errors that can occur while reading BMP images
+#[derive(Debug)]
+pub enum BmpError {
+    Io(io::Error),
+    /// The image is compressed, uses an OS/2 header or a bit depth other than 1, 4, 8, 24 or 32
+    UnsupportedFormat,
+    /// The headers are missing or malformed
+    InvalidHeader,
+    /// The palette or pixel data is truncated
+    InvalidData,
+    /// The image size does not fit the scale and quiet zone of a square QR code
+    InvalidSize {
+        width: usize,
+        height: usize,
+    },
+}
+
+impl Display for BmpError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BmpError::Io(error) => write!(f, "{}", error),
+            BmpError::UnsupportedFormat => write!(f, "unsupported bmp format"),
+            BmpError::InvalidHeader => write!(f, "invalid bmp header"),
+            BmpError::InvalidData => write!(f, "invalid or truncated pixel data"),
+            BmpError::InvalidSize { width, height } => {
+                write!(f, "image size {}x{} does not fit a QR code", width, height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BmpError {}
+
+impl From<io::Error> for BmpError {
+    fn from(error: io::Error) -> Self {
+        BmpError::Io(error)
+    }
+}
+
+/// Number of bytes of a pixel row, rows are padded to a multiple of 4 bytes
+fn row_size(width: usize, bits_per_pixel: usize) -> usize {
+    (width * bits_per_pixel).div_ceil(32) * 4
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, BmpError> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or(BmpError::InvalidHeader)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, BmpError> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(BmpError::InvalidHeader)
+}
+
+/// Extracts a colour component selected by a `BI_BITFIELDS` mask, scaled to 0 - 255
+fn masked_component(pixel: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let value = (pixel & mask) >> mask.trailing_zeros();
+    let max_value = mask >> mask.trailing_zeros();
+
+    (value as u64 * 255 / max_value as u64) as u8
+}
+
+impl GrayImage {
+    /// Reads an uncompressed 1, 4, 8, 24 or 32-bit BMP image
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the image data
+    ///
+    /// # Returns
+    ///
+    /// The image with the luma of every pixel, or a `BmpError` if it cannot be read
+    pub fn read_bmp<R: Read>(mut reader: R) -> Result<GrayImage, BmpError> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        if data.get(..2) != Some(b"BM") {
+            return Err(BmpError::InvalidHeader);
+        }
+
+        let pixel_offset = read_u32(&data, 10)? as usize;
+        let header_size = read_u32(&data, 14)? as usize;
+
+        // The 12 byte OS/2 header stores the size in 16-bit fields
+        if header_size < INFO_HEADER_SIZE {
+            return Err(BmpError::UnsupportedFormat);
+        }
+
+        let width = read_u32(&data, 18)? as i32;
+        let height = read_u32(&data, 22)? as i32;
+        let bits_per_pixel = read_u16(&data, 28)? as usize;
+        let compression = read_u32(&data, 30)?;
+        let colors_used = read_u32(&data, 46)? as usize;
+
+        if width <= 0 || height == 0 {
+            return Err(BmpError::InvalidHeader);
+        }
+
+        // A negative height marks rows stored top-down
+        let top_down = height < 0;
+        let (width, height) = (width as usize, height.unsigned_abs() as usize);
+
+        let masks = match (compression, bits_per_pixel) {
+            (BI_RGB, 1 | 4 | 8 | 24) => None,
+            (BI_RGB, 32) => Some([0x00FF_0000, 0x0000_FF00, 0x0000_00FF]),
+            // The masks follow a BITMAPINFOHEADER and are part of the larger headers
+            (BI_BITFIELDS, 32) => Some([
+                read_u32(&data, FILE_HEADER_SIZE + INFO_HEADER_SIZE)?,
+                read_u32(&data, FILE_HEADER_SIZE + INFO_HEADER_SIZE + 4)?,
+                read_u32(&data, FILE_HEADER_SIZE + INFO_HEADER_SIZE + 8)?,
+            ]),
+            _ => return Err(BmpError::UnsupportedFormat),
+        };
+
+        let palette: Vec<Color> = if bits_per_pixel <= 8 {
+            let entries = match colors_used {
+                0 => 1 << bits_per_pixel,
+                entries => entries,
+            };
+            let start = FILE_HEADER_SIZE + header_size;
+
+            data.get(start..start + 4 * entries)
+                .ok_or(BmpError::InvalidData)?
+                .chunks(4)
+                .map(|entry| Color::rgb(entry[2], entry[1], entry[0]))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let row_bytes = row_size(width, bits_per_pixel);
+        let raster = row_bytes
+            .checked_mul(height)
+            .and_then(|size| data.get(pixel_offset..pixel_offset.checked_add(size)?))
+            .ok_or(BmpError::InvalidData)?;
+
+        let mut pixels: Vec<u8> = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let row = if top_down { y } else { height - 1 - y };
+            let row = &raster[row * row_bytes..(row + 1) * row_bytes];
+
+            for x in 0..width {
+                let color = match (bits_per_pixel, masks) {
+                    (24, _) => Color::rgb(row[3 * x + 2], row[3 * x + 1], row[3 * x]),
+                    (32, Some([red, green, blue])) => {
+                        let bytes = &row[4 * x..4 * x + 4];
+                        let pixel = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+                        Color::rgb(
+                            masked_component(pixel, red),
+                            masked_component(pixel, green),
+                            masked_component(pixel, blue),
+                        )
+                    }
+                    _ => {
+                        // Palette indices are packed from the most significant bit
+                        let bit = x * bits_per_pixel;
+                        let shift = 8 - bits_per_pixel - bit % 8;
+                        let index = (row[bit / 8] >> shift) & ((1 << bits_per_pixel) - 1) as u8;
+
+                        *palette.get(index as usize).ok_or(BmpError::InvalidData)?
+                    }
+                };
+
+                pixels.push(color.luma());
+            }
+        }
+
+        Ok(GrayImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Writes a BMP image pixel by pixel
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the image data
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `bmp_options` - Pixel format and resolution of the image
+/// * `palette` - Light and dark colour of the indexed formats
+/// * `pixel` - Whether the pixel at a column and row is dark, and its colour
+pub(crate) fn write_bmp_image<W: Write + ?Sized>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    bmp_options: &BmpOptions,
+    palette: [Color; 2],
+    pixel: impl Fn(usize, usize) -> (bool, Color),
+) -> io::Result<()> {
+    let bits_per_pixel = bmp_options.format.bits_per_pixel();
+    let row_bytes = row_size(width, bits_per_pixel);
+
+    let palette_entries = if bits_per_pixel <= 8 { 2 } else { 0 };
+    let pixel_offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE + 4 * palette_entries;
+    let image_size = row_bytes * height;
+    let resolution = pixels_per_metre(bmp_options.dpi);
+
+    let mut header: Vec<u8> = Vec::with_capacity(pixel_offset);
+
+    // File header: signature, file size, two reserved words and the offset of the pixels
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&((pixel_offset + image_size) as u32).to_le_bytes());
+    header.extend_from_slice(&[0, 0, 0, 0]);
+    header.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+
+    // BITMAPINFOHEADER, a positive height stores the rows bottom-up
+    header.extend_from_slice(&(INFO_HEADER_SIZE as u32).to_le_bytes());
+    header.extend_from_slice(&(width as i32).to_le_bytes());
+    header.extend_from_slice(&(height as i32).to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&(bits_per_pixel as u16).to_le_bytes());
+    header.extend_from_slice(&BI_RGB.to_le_bytes());
+    header.extend_from_slice(&(image_size as u32).to_le_bytes());
+    header.extend_from_slice(&resolution.to_le_bytes());
+    header.extend_from_slice(&resolution.to_le_bytes());
+    header.extend_from_slice(&(palette_entries as u32).to_le_bytes());
+    header.extend_from_slice(&(palette_entries as u32).to_le_bytes());
+
+    // The light colour is the first palette entry, stored as blue, green, red
+    if palette_entries > 0 {
+        for color in palette {
+            header.extend_from_slice(&[color.b, color.g, color.r, 0]);
+        }
+    }
+
+    writer.write_all(&header)?;
+
+    for y in (0..height).rev() {
+        let mut row = vec![0u8; row_bytes];
+
+        for x in 0..width {
+            let (dark, color) = pixel(x, y);
+
+            match bmp_options.format {
+                BmpFormat::Indexed1 if dark => row[x / 8] |= 0x80 >> (x % 8),
+                BmpFormat::Indexed1 => {}
+                BmpFormat::Indexed8 => row[x] = dark as u8,
+                BmpFormat::Rgb24 => {
+                    row[3 * x..3 * x + 3].copy_from_slice(&[color.b, color.g, color.r])
+                }
+                BmpFormat::Rgba32 => {
+                    row[4 * x..4 * x + 4].copy_from_slice(&[color.b, color.g, color.r, 255])
+                }
+            }
+        }
+
+        writer.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+impl BitMap {
+    /// Writes the bitmap as a BMP image
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `bmp_options` - Pixel format and resolution of the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn write_bmp<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        bmp_options: &BmpOptions,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let width = options.image_size(self);
+
+        write_bmp_image(
+            writer,
+            width,
+            width,
+            bmp_options,
+            [options.light, options.dark],
+            |x, y| {
+                (
+                    self.is_dark_pixel(x, y, options),
+                    self.pixel_color(x, y, options),
+                )
+            },
+        )
+    }
+
+    /// Saves the bitmap to a file in BMP format
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `bmp_options` - Pixel format and resolution of the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn save_bmp<P>(
+        &self,
+        path: P,
+        bmp_options: &BmpOptions,
+        options: &RenderOptions,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_bmp(&mut writer, bmp_options, options)?;
+        writer.flush()
+    }
+
+    /// Reads a BMP image of a QR code back into a bitmap
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the image data
+    /// * `options` - Module size, quiet zone and colours the image was written with
+    ///
+    /// # Returns
+    ///
+    /// The bitmap with the pixel at the centre of every module thresholded halfway between the
+    /// luma of the colours, or a `BmpError` if the image cannot be read or does not fit the
+    /// module size and quiet zone
+    pub fn read_bmp<R: Read>(reader: R, options: &RenderOptions) -> Result<BitMap, BmpError> {
+        let image = GrayImage::read_bmp(reader)?;
+
+        image.to_bit_map(options).ok_or(BmpError::InvalidSize {
+            width: image.width,
+            height: image.height,
+        })
+    }
+}
+
+/// Writes BMP images with these options
+impl Renderer for BmpOptions {
+    fn render(
+        &self,
+        bit_map: &BitMap,
+        writer: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        bit_map.write_bmp(writer, self, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bmp() {
+        assert_eq!(pixels_per_metre(96), 3780);
+        assert_eq!(pixels_per_metre(300), 11811);
+
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, 1);
+
+        let options = RenderOptions {
+            module_px: 3,
+            quiet_zone_modules: 1,
+            dark: Color::rgb(0, 0, 128),
+            ..RenderOptions::default()
+        };
+
+        let mut bmp: Vec<u8> = Vec::new();
+        bit_map
+            .write_bmp(&mut bmp, &BmpOptions::default(), &options)
+            .unwrap();
+
+        // 9 x 9 pixels in rows of 4 bytes after the headers and the two colour palette
+        assert_eq!(bmp.len(), 62 + 9 * 4);
+        assert_eq!(bmp[2..6], 98u32.to_le_bytes());
+        assert_eq!(bmp[10..14], 62u32.to_le_bytes());
+        assert_eq!(bmp[38..42], 3780u32.to_le_bytes());
+        assert_eq!(bmp[54..62], [255, 255, 255, 0, 128, 0, 0, 0]);
+        assert_eq!(bmp[62..66], [0, 0, 0, 0]);
+        assert_eq!(bmp[74..78], [0x1C, 0, 0, 0]);
+
+        let mut default_bmp: Vec<u8> = Vec::new();
+        bit_map.write_bmp_default(&mut default_bmp).unwrap();
+        let mut bmp: Vec<u8> = Vec::new();
+        bit_map
+            .write_bmp(&mut bmp, &BmpOptions::default(), &RenderOptions::default())
+            .unwrap();
+        assert_eq!(default_bmp, bmp);
+
+        let bmp_options = BmpOptions {
+            format: BmpFormat::Rgb24,
+            dpi: 300,
+        };
+
+        let mut bmp: Vec<u8> = Vec::new();
+        bit_map.write_bmp(&mut bmp, &bmp_options, &options).unwrap();
+
+        // 27 bytes of pixels padded to 28
+        assert_eq!(bmp.len(), 54 + 9 * 28);
+        assert_eq!(bmp[54 + 3 * 28 + 9..54 + 3 * 28 + 12], [128, 0, 0]);
+    }
+
+    #[test]
+    fn test_read_bmp() {
+        let mut bit_map = BitMap::new(5);
+        for (i, j) in [(0, 0), (1, 3), (4, 4), (2, 1)] {
+            bit_map.set(i, j, 1);
+        }
+
+        let options = RenderOptions {
+            module_px: 3,
+            quiet_zone_modules: 2,
+            dark: Color::rgb(0, 0, 128),
+            ..RenderOptions::default()
+        };
+
+        for format in [
+            BmpFormat::Indexed1,
+            BmpFormat::Indexed8,
+            BmpFormat::Rgb24,
+            BmpFormat::Rgba32,
+        ] {
+            let bmp_options = BmpOptions {
+                format,
+                ..BmpOptions::default()
+            };
+
+            let mut bmp: Vec<u8> = Vec::new();
+            bit_map.write_bmp(&mut bmp, &bmp_options, &options).unwrap();
+
+            let read = BitMap::read_bmp(bmp.as_slice(), &options).unwrap();
+            for i in 0..5 {
+                for j in 0..5 {
+                    assert_eq!(read.get(i, j), bit_map.get(i, j));
+                }
+            }
+        }
+
+        // The options are a renderer as well
+        let mut bmp: Vec<u8> = Vec::new();
+        bit_map
+            .render(&BmpOptions::default(), &mut bmp, &options)
+            .unwrap();
+        assert_eq!(
+            BitMap::read_bmp(bmp.as_slice(), &options).unwrap().size(),
+            5
+        );
+
+        assert!(matches!(
+            GrayImage::read_bmp(&b"GIF89a"[..]),
+            Err(BmpError::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,547 @@
+//! # Caption
+//!
+//! Human-readable text below a QR code, such as the ID of an asset tag. Raster images draw the
+//! text with the built-in 5 x 7 pixel font, SVG images set it as `<text>` in a monospace font.
+//! The text goes below the quiet zone, one line per line of the caption, aligned with the edges
+//! of the symbol, and the image grows to fit it, also sideways when a line is wider than the
+//! image.
+//!
+//! ```rust,ignore
+//! let caption = Caption {
+//!     text: "ASSET-000042".to_string(),
+//!     ..Caption::default()
+//! };
+//! qr_code.save_png_with_caption("tag.png", &RenderOptions::default(), &caption)?;
+//! ```
+
+use super::bmp::{write_bmp_image, BmpOptions};
+use super::font::{glyph_pixel, ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+use super::png::write_rgba_png;
+use super::svg::SvgOptions;
+use super::{format_number, RenderOptions};
+use crate::bit_utils::bitmap::BitMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Width of a character of the SVG monospace font relative to the font size
+const SVG_ADVANCE: f64 = 0.6;
+
+/// Height above the baseline of the SVG font relative to the font size
+const SVG_ASCENT: f64 = 0.8;
+
+/// Horizontal alignment of the caption lines
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CaptionAlign {
+    /// Starts at the left edge of the symbol
+    Left,
+    /// Centred below the symbol
+    Centre,
+    /// Ends at the right edge of the symbol
+    Right,
+}
+
+/// Text written below a QR code
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Caption {
+    /// The text, split into lines at line breaks
+    pub text: String,
+    /// Size of a pixel of the font in image pixels, or in user units for SVG, 0 is treated as 1
+    pub scale: usize,
+    /// Space above and below the text in image pixels, or in user units for SVG
+    pub spacing: usize,
+    pub align: CaptionAlign,
+}
+
+impl Default for Caption {
+    /// An empty caption of 2 pixel font pixels, centred with 8 pixels of space
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            scale: 2,
+            spacing: 8,
+            align: CaptionAlign::Centre,
+        }
+    }
+}
+
+/// Size of the image grown to fit a caption and the position of the caption lines, in pixels
+/// for raster images and in user units for SVG
+#[derive(PartialEq, Debug)]
+struct CaptionLayout<T> {
+    /// Distance the symbol moves to the right to make room for wide lines
+    offset: T,
+    width: T,
+    height: T,
+    /// Left edge and top of every line
+    lines: Vec<(T, T)>,
+}
+
+impl Caption {
+    /// Aligns the lines with the edges of the symbol
+    ///
+    /// # Arguments
+    ///
+    /// * `image_size` - Width and height of the image with the quiet zone
+    /// * `quiet_zone` - Width of the quiet zone
+    /// * `line_widths` - Width of every line
+    ///
+    /// # Returns
+    ///
+    /// The left edge of every line, negative for lines sticking out on the left
+    fn line_lefts(&self, image_size: f64, quiet_zone: f64, line_widths: &[f64]) -> Vec<f64> {
+        let symbol_size = image_size - 2.0 * quiet_zone;
+
+        line_widths
+            .iter()
+            .map(|width| match self.align {
+                CaptionAlign::Left => quiet_zone,
+                CaptionAlign::Centre => quiet_zone + (symbol_size - width) / 2.0,
+                CaptionAlign::Right => image_size - quiet_zone - width,
+            })
+            .collect()
+    }
+
+    /// Lays the lines out below an image of the symbol
+    ///
+    /// # Arguments
+    ///
+    /// * `image_size` - Width and height of the image with the quiet zone
+    /// * `lefts` - Left edge of every line, from `line_lefts`
+    /// * `line_widths` - Width of every line
+    /// * `line_height` - Distance between the tops of two lines
+    /// * `text_height` - Height of all the lines together
+    /// * `spacing` - Space above and below the text
+    fn layout(
+        &self,
+        image_size: f64,
+        lefts: &[f64],
+        line_widths: &[f64],
+        line_height: f64,
+        text_height: f64,
+        spacing: f64,
+    ) -> CaptionLayout<f64> {
+        // Without text the image keeps its size
+        let spacing = if line_widths.is_empty() { 0.0 } else { spacing };
+
+        // Lines sticking out of the image on either side widen it
+        let offset = lefts.iter().fold(0.0f64, |offset, left| offset.max(-left));
+        let right = lefts
+            .iter()
+            .zip(line_widths)
+            .fold(image_size, |right, (left, width)| right.max(left + width));
+
+        CaptionLayout {
+            offset,
+            width: offset + right,
+            height: image_size + 2.0 * spacing + text_height,
+            lines: lefts
+                .iter()
+                .enumerate()
+                .map(|(line, left)| {
+                    (
+                        left + offset,
+                        image_size + spacing + line as f64 * line_height,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Lays the caption out in the built-in font below a raster image
+    ///
+    /// Centred lines are moved left to the nearest whole pixel before the image grows around
+    /// them, so the offset, size and line positions are all whole pixels
+    fn raster_layout(&self, image_size: usize, quiet_zone: usize) -> CaptionLayout<usize> {
+        let scale = self.scale.max(1);
+        let lines: Vec<&str> = self.text.lines().collect();
+
+        // The blank column after the last character and the blank row after the last line
+        // are left out
+        let line_widths: Vec<f64> = lines
+            .iter()
+            .map(|line| (line.chars().count() * ADVANCE).saturating_sub(1) * scale)
+            .map(|width| width as f64)
+            .collect();
+        let text_height = (lines.len() * LINE_HEIGHT).saturating_sub(1) * scale;
+        let lefts: Vec<f64> = self
+            .line_lefts(image_size as f64, quiet_zone as f64, &line_widths)
+            .into_iter()
+            .map(f64::floor)
+            .collect();
+
+        let layout = self.layout(
+            image_size as f64,
+            &lefts,
+            &line_widths,
+            (LINE_HEIGHT * scale) as f64,
+            text_height as f64,
+            self.spacing as f64,
+        );
+
+        CaptionLayout {
+            offset: layout.offset as usize,
+            width: layout.width as usize,
+            height: layout.height as usize,
+            lines: layout
+                .lines
+                .into_iter()
+                .map(|(left, top)| (left as usize, top as usize))
+                .collect(),
+        }
+    }
+
+    /// Checks whether a pixel of a raster image is part of the text
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - Layout of the caption in pixels
+    /// * `x` - Pixel column, counted from the left of the grown image
+    /// * `y` - Pixel row, counted from the top of the grown image
+    fn is_text_pixel(&self, layout: &CaptionLayout<usize>, x: usize, y: usize) -> bool {
+        let scale = self.scale.max(1);
+
+        self.text
+            .lines()
+            .zip(&layout.lines)
+            .any(|(line, &(left, top))| {
+                if x < left || y < top || y >= top + GLYPH_HEIGHT * scale {
+                    return false;
+                }
+
+                let (column, row) = ((x - left) / scale, (y - top) / scale);
+
+                line.chars()
+                    .nth(column / ADVANCE)
+                    .is_some_and(|character| glyph_pixel(character, column % ADVANCE, row))
+            })
+    }
+}
+
+/// Escapes text for XML character data
+fn xml_escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            _ => output.push(character),
+        }
+    }
+
+    output
+}
+
+impl BitMap {
+    /// Renders the bitmap with a caption as an 8-bit RGBA pixel buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    ///
+    /// # Returns
+    ///
+    /// The width, height and pixels of the grown image, four bytes per pixel with opaque alpha
+    pub fn to_rgba8_with_caption(
+        &self,
+        options: &RenderOptions,
+        caption: &Caption,
+    ) -> (usize, usize, Vec<u8>) {
+        let (width, height, pixels) = self.to_rgba8(options);
+        let layout = caption.raster_layout(width, options.quiet_zone_modules * options.scale());
+        let (grown_width, grown_height, offset) = (layout.width, layout.height, layout.offset);
+
+        let mut grown = Vec::with_capacity(grown_width * grown_height * 4);
+
+        for y in 0..grown_height {
+            for x in 0..grown_width {
+                if y < height && (offset..offset + width).contains(&x) {
+                    let index = 4 * (y * width + x - offset);
+                    grown.extend_from_slice(&pixels[index..index + 4]);
+                    continue;
+                }
+
+                let color = if caption.is_text_pixel(&layout, x, y) {
+                    options.dark
+                } else {
+                    options.light
+                };
+                grown.extend_from_slice(&[color.r, color.g, color.b, 255]);
+            }
+        }
+
+        (grown_width, grown_height, grown)
+    }
+
+    /// Writes the bitmap as a PNG image with a caption
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Module size, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    pub fn write_png_with_caption<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+        caption: &Caption,
+    ) -> io::Result<()> {
+        let (width, height, pixels) = self.to_rgba8_with_caption(options, caption);
+        write_rgba_png(writer, width, height, &pixels, None)
+    }
+
+    /// Saves the bitmap to a file in PNG format with a caption
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Module size, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    pub fn save_png_with_caption<P>(
+        &self,
+        path: P,
+        options: &RenderOptions,
+        caption: &Caption,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png_with_caption(&mut writer, options, caption)?;
+        writer.flush()
+    }
+
+    /// Writes the bitmap as a BMP image with a caption
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `bmp_options` - Pixel format and resolution of the image
+    /// * `options` - Module size, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    pub fn write_bmp_with_caption<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        bmp_options: &BmpOptions,
+        options: &RenderOptions,
+        caption: &Caption,
+    ) -> io::Result<()> {
+        let image_size = options.image_size(self);
+        let layout =
+            caption.raster_layout(image_size, options.quiet_zone_modules * options.scale());
+        let offset = layout.offset;
+
+        write_bmp_image(
+            writer,
+            layout.width,
+            layout.height,
+            bmp_options,
+            [options.light, options.dark],
+            |x, y| {
+                if y < image_size && (offset..offset + image_size).contains(&x) {
+                    let x = x - offset;
+                    (
+                        self.is_dark_pixel(x, y, options),
+                        self.pixel_color(x, y, options),
+                    )
+                } else if caption.is_text_pixel(&layout, x, y) {
+                    (true, options.dark)
+                } else {
+                    (false, options.light)
+                }
+            },
+        )
+    }
+
+    /// Saves the bitmap to a file in BMP format with a caption
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `bmp_options` - Pixel format and resolution of the image
+    /// * `options` - Module size, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    pub fn save_bmp_with_caption<P>(
+        &self,
+        path: P,
+        bmp_options: &BmpOptions,
+        options: &RenderOptions,
+        caption: &Caption,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_bmp_with_caption(&mut writer, bmp_options, options, caption)?;
+        writer.flush()
+    }
+
+    /// Writes the bitmap as an SVG image with a caption set as text
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Scale, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    pub fn write_svg_with_caption<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &SvgOptions,
+        caption: &Caption,
+    ) -> io::Result<()> {
+        // The viewBox is measured in modules, the caption in user units
+        let unit = 1.0 / options.scale.max(1) as f64;
+        let font_size = (LINE_HEIGHT * caption.scale.max(1)) as f64 * unit;
+        let lines: Vec<&str> = caption.text.lines().collect();
+
+        let line_widths: Vec<f64> = lines
+            .iter()
+            .map(|line| line.chars().count() as f64 * SVG_ADVANCE * font_size)
+            .collect();
+        let image_size = (self.size() + 2 * options.quiet_zone) as f64;
+        let layout = caption.layout(
+            image_size,
+            &caption.line_lefts(image_size, options.quiet_zone as f64, &line_widths),
+            &line_widths,
+            font_size,
+            lines.len() as f64 * font_size,
+            caption.spacing as f64 * unit,
+        );
+        let n = format_number;
+
+        // The symbol stays at the origin and the image grows to the left instead
+        self.write_svg_elements(
+            writer,
+            options,
+            Some((-layout.offset, 0.0, layout.width, layout.height)),
+        )?;
+
+        let anchor = match caption.align {
+            CaptionAlign::Left => "start",
+            CaptionAlign::Centre => "middle",
+            CaptionAlign::Right => "end",
+        };
+
+        for ((line, (left, top)), width) in lines.iter().zip(&layout.lines).zip(&line_widths) {
+            let x = match caption.align {
+                CaptionAlign::Left => *left,
+                CaptionAlign::Centre => left + width / 2.0,
+                CaptionAlign::Right => left + width,
+            };
+
+            writeln!(
+                writer,
+                r#"<text x="{}" y="{}" font-family="monospace" font-size="{}" text-anchor="{}" fill="{}">{}</text>"#,
+                n(x - layout.offset),
+                n(top + SVG_ASCENT * font_size),
+                n(font_size),
+                anchor,
+                options.dark,
+                xml_escape(line)
+            )?;
+        }
+
+        writeln!(writer, "</svg>")
+    }
+
+    /// Saves the bitmap to a file in SVG format with a caption set as text
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Scale, quiet zone and colours of the image, the text is dark
+    /// * `caption` - Text below the QR code
+    pub fn save_svg_with_caption<P>(
+        &self,
+        path: P,
+        options: &SvgOptions,
+        caption: &Caption,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_svg_with_caption(&mut writer, options, caption)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_utils::bit::Bit;
+
+    #[test]
+    fn test_caption() {
+        let bit_map = BitMap::new(1);
+        let options = RenderOptions {
+            module_px: 2,
+            quiet_zone_modules: 1,
+            ..RenderOptions::default()
+        };
+        let caption = Caption {
+            text: "I".to_string(),
+            scale: 1,
+            spacing: 2,
+            ..Caption::default()
+        };
+
+        // A 6 x 6 image grows by the spacing above and below and 7 rows of text
+        let (width, height, pixels) = bit_map.to_rgba8_with_caption(&options, &caption);
+        assert_eq!((width, height), (6, 17));
+
+        // The stem of the centred 'I' is in column 2 of the glyph
+        let dark = |x: usize, y: usize| pixels[4 * (y * width + x)] == 0;
+        assert!(dark(2, 8) && dark(2, 14) && !dark(2, 15));
+        assert!(dark(1, 8) && !dark(1, 9));
+
+        // A left aligned line wider than the image only widens it to the right
+        let caption = Caption {
+            text: "AB\nLONGER".to_string(),
+            align: CaptionAlign::Left,
+            ..caption
+        };
+        let layout = caption.raster_layout(6, 2);
+        assert_eq!(layout.offset, 0);
+        assert_eq!(layout.width, 2 + 35);
+        assert_eq!(layout.lines, vec![(2, 8), (2, 16)]);
+
+        // Centred lines half a pixel off the grid move left, and the image grows around them
+        let caption = Caption {
+            align: CaptionAlign::Centre,
+            ..caption
+        };
+        let layout = caption.raster_layout(6, 2);
+        assert_eq!(layout.offset, 15);
+        assert_eq!(layout.width, 35);
+        assert_eq!(layout.lines, vec![(12, 8), (0, 16)]);
+
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, Bit::One);
+
+        let mut svg: Vec<u8> = Vec::new();
+        bit_map
+            .write_svg_with_caption(
+                &mut svg,
+                &SvgOptions {
+                    scale: 4,
+                    quiet_zone: 1,
+                    ..SvgOptions::default()
+                },
+                &Caption {
+                    text: "<ID>".to_string(),
+                    ..Caption::default()
+                },
+            )
+            .unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        // Font pixels of 2 user units are half a module, so the text is 4 modules high and its
+        // line is wider than the image
+        assert!(svg.contains(r#"viewBox="-3.3 0 9.6 11""#));
+        assert!(svg.contains(r##"<text x="1.5" y="8.2" font-family="monospace" font-size="4" text-anchor="middle" fill="#000000">&lt;ID&gt;</text>"##));
+    }
+}
@@ -0,0 +1,354 @@
+//! # Deflate
+//!
+//! A small zlib/deflate (RFC 1950/1951) encoder for the image writers. Data is compressed with
+//! LZ77 matching into a single fixed Huffman block, and falls back to stored blocks when that
+//! would not be smaller.
+
+// Length codes 257 - 285: (base length, extra bits)
+const LENGTH_CODES: [(u16, u8); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+// Distance codes 0 - 29: (base distance, extra bits)
+const DISTANCE_CODES: [(u16, u8); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_SIZE: usize = 1 << 15;
+
+/// Writes bits least significant bit first, the way deflate packs them
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u32,
+    current_len: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            current: 0,
+            current_len: 0,
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, len: u8) {
+        for i in 0..len {
+            self.current |= ((value >> i) & 1) << self.current_len;
+            self.current_len += 1;
+
+            if self.current_len == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.current_len = 0;
+            }
+        }
+    }
+
+    /// Huffman codes are packed starting from their most significant bit
+    fn write_code(&mut self, code: u32, len: u8) {
+        for i in (0..len).rev() {
+            self.write_bits((code >> i) & 1, 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.current_len > 0 {
+            self.bytes.push(self.current as u8);
+        }
+
+        self.bytes
+    }
+}
+
+/// Literal or back reference found by the LZ77 matcher
+enum Token {
+    Literal(u8),
+    Match { length: usize, distance: usize },
+}
+
+fn hash(data: &[u8], index: usize) -> usize {
+    let value = ((data[index] as usize) << 10) ^ ((data[index + 1] as usize) << 5);
+    (value ^ data[index + 2] as usize) & (HASH_SIZE - 1)
+}
+
+/// Positions of earlier 3 byte sequences, chained by their hash
+struct HashChains {
+    head: Vec<Option<usize>>,
+    previous: Vec<Option<usize>>,
+}
+
+impl HashChains {
+    fn new(len: usize) -> Self {
+        Self {
+            head: vec![None; HASH_SIZE],
+            previous: vec![None; len],
+        }
+    }
+
+    fn insert(&mut self, data: &[u8], index: usize) {
+        if index + MIN_MATCH <= data.len() {
+            let key = hash(data, index);
+            self.previous[index] = self.head[key];
+            self.head[key] = Some(index);
+        }
+    }
+
+    /// Returns the length and distance of the longest match for the data at the index
+    fn longest_match(&self, data: &[u8], index: usize) -> (usize, usize) {
+        let mut best = (0, 0);
+
+        if index + MIN_MATCH > data.len() {
+            return best;
+        }
+
+        let max_length = MAX_MATCH.min(data.len() - index);
+        let mut candidate = self.head[hash(data, index)];
+        let mut chain = 0;
+
+        while let Some(position) = candidate {
+            if index - position > WINDOW_SIZE || chain >= MAX_CHAIN {
+                break;
+            }
+
+            let length = (0..max_length)
+                .take_while(|offset| data[position + offset] == data[index + offset])
+                .count();
+
+            if length > best.0 {
+                best = (length, index - position);
+
+                if length == max_length {
+                    break;
+                }
+            }
+
+            candidate = self.previous[position];
+            chain += 1;
+        }
+
+        best
+    }
+}
+
+/// Finds back references with hash chains over a 32 KiB window
+fn lz77(data: &[u8]) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::with_capacity(data.len());
+    let mut chains = HashChains::new(data.len());
+
+    let mut index = 0;
+    while index < data.len() {
+        let (length, distance) = chains.longest_match(data, index);
+
+        if length >= MIN_MATCH {
+            tokens.push(Token::Match { length, distance });
+
+            for position in index..index + length {
+                chains.insert(data, position);
+            }
+            index += length;
+        } else {
+            tokens.push(Token::Literal(data[index]));
+            chains.insert(data, index);
+            index += 1;
+        }
+    }
+
+    tokens
+}
+
+fn write_literal_length(writer: &mut BitWriter, symbol: u16) {
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol as u32, 8),
+        144..=255 => writer.write_code(0x190 + (symbol - 144) as u32, 9),
+        256..=279 => writer.write_code((symbol - 256) as u32, 7),
+        _ => writer.write_code(0xC0 + (symbol - 280) as u32, 8),
+    }
+}
+
+/// Compresses the data into a single fixed Huffman block
+fn fixed_huffman_block(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    for token in lz77(data) {
+        match token {
+            Token::Literal(byte) => write_literal_length(&mut writer, byte as u16),
+            Token::Match { length, distance } => {
+                let code = LENGTH_CODES
+                    .iter()
+                    .rposition(|(base, _)| *base as usize <= length)
+                    .unwrap_or(0);
+                let (base, extra_bits) = LENGTH_CODES[code];
+                write_literal_length(&mut writer, 257 + code as u16);
+                writer.write_bits((length - base as usize) as u32, extra_bits);
+
+                let code = DISTANCE_CODES
+                    .iter()
+                    .rposition(|(base, _)| *base as usize <= distance)
+                    .unwrap_or(0);
+                let (base, extra_bits) = DISTANCE_CODES[code];
+                writer.write_code(code as u32, 5);
+                writer.write_bits((distance - base as usize) as u32, extra_bits);
+            }
+        }
+    }
+
+    // End of block
+    write_literal_length(&mut writer, 256);
+
+    writer.finish()
+}
+
+/// Stores the data uncompressed in blocks of at most 65535 bytes
+fn stored_blocks(data: &[u8]) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(data.len() + 5 * (data.len() / 65535 + 1));
+    let mut chunks = data.chunks(65535).peekable();
+
+    if chunks.peek().is_none() {
+        // An empty final stored block
+        output.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let final_block = chunks.peek().is_none() as u8;
+        let len = chunk.len() as u16;
+
+        output.push(final_block);
+        output.extend_from_slice(&len.to_le_bytes());
+        output.extend_from_slice(&(!len).to_le_bytes());
+        output.extend_from_slice(chunk);
+    }
+
+    output
+}
+
+/// Computes the Adler-32 checksum of the data
+pub fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % MODULUS;
+        b = (b + a) % MODULUS;
+    }
+
+    (b << 16) | a
+}
+
+/// Compresses the data into a zlib stream
+///
+/// # Arguments
+///
+/// * `data` - The data to compress
+///
+/// # Returns
+///
+/// The zlib header, the deflate blocks and the Adler-32 checksum of the data
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // 32 KiB window, deflate, fastest compression level, header checksum
+    let mut output: Vec<u8> = vec![0x78, 0x01];
+
+    let compressed = fixed_huffman_block(data);
+    let stored = stored_blocks(data);
+
+    if compressed.len() < stored.len() {
+        output.extend_from_slice(&compressed);
+    } else {
+        output.extend_from_slice(&stored);
+    }
+
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zlib_compress() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        assert_eq!(
+            zlib_compress(&[0xFF]),
+            vec![0x78, 0x01, 0xFB, 0x0F, 0x00, 0x01, 0x00, 0x01, 0x00]
+        );
+
+        // Highly repetitive data ends up as a fixed Huffman block with back references
+        let data = vec![0u8; 1000];
+        let compressed = zlib_compress(&data);
+        assert_eq!(compressed[2] & 0b111, 0b011);
+        assert!(compressed.len() < 20);
+
+        // Data without repetitions is smaller stored
+        let data: Vec<u8> = (0..=255).collect();
+        let compressed = zlib_compress(&data);
+        assert_eq!(compressed[2..7], [1, 0, 1, 0xFF, 0xFE]);
+        assert_eq!(compressed.len(), 2 + 5 + 256 + 4);
+    }
+}
@@ -0,0 +1,177 @@
+//! # EPS
+//!
+//! An Encapsulated PostScript writer for print workflows. Modules have a physical size in
+//! points, colours can be given in RGB or CMYK, and every horizontal run of dark modules is
+//! drawn with a single `rectfill`.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! qr_code.save_eps("qr_code.eps", &EpsOptions::default())?;
+//! ```
+
+use super::{format_number, Color};
+use crate::bit_utils::bitmap::BitMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Colour of EPS output
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum EpsColor {
+    Rgb(Color),
+    /// Cyan, magenta, yellow and black components between 0.0 and 1.0, others are clamped
+    Cmyk {
+        c: f64,
+        m: f64,
+        y: f64,
+        k: f64,
+    },
+}
+
+impl EpsColor {
+    /// PostScript command selecting the colour
+    fn set_color(&self) -> String {
+        match self {
+            EpsColor::Rgb(color) => format!(
+                "{} {} {} setrgbcolor",
+                format_number(color.r as f64 / 255.0),
+                format_number(color.g as f64 / 255.0),
+                format_number(color.b as f64 / 255.0)
+            ),
+            EpsColor::Cmyk { c, m, y, k } => format!(
+                "{} {} {} {} setcmykcolor",
+                format_number(c.clamp(0.0, 1.0)),
+                format_number(m.clamp(0.0, 1.0)),
+                format_number(y.clamp(0.0, 1.0)),
+                format_number(k.clamp(0.0, 1.0))
+            ),
+        }
+    }
+}
+
+/// Options for writing EPS images
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct EpsOptions {
+    /// Size of a module in points (1/72 inch)
+    pub module_size: f64,
+    /// Width of the quiet zone around the symbol in modules
+    pub quiet_zone: usize,
+    /// Colour of the dark modules
+    pub dark: EpsColor,
+    /// Colour of the light modules and the quiet zone, `None` leaves them unpainted
+    pub light: Option<EpsColor>,
+}
+
+impl Default for EpsOptions {
+    fn default() -> Self {
+        Self {
+            module_size: 2.0,
+            quiet_zone: 4,
+            dark: EpsColor::Rgb(Color::BLACK),
+            light: Some(EpsColor::Rgb(Color::WHITE)),
+        }
+    }
+}
+
+impl BitMap {
+    /// Writes the bitmap as an Encapsulated PostScript image
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the PostScript program
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn write_eps<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &EpsOptions,
+    ) -> io::Result<()> {
+        let modules = self.size() + 2 * options.quiet_zone;
+        let width = modules as f64 * options.module_size;
+
+        writeln!(writer, "%!PS-Adobe-3.0 EPSF-3.0")?;
+        writeln!(writer, "%%Creator: DEWQ")?;
+        writeln!(writer, "%%BoundingBox: 0 0 {0} {0}", width.ceil() as u64)?;
+        writeln!(
+            writer,
+            "%%HiResBoundingBox: 0 0 {0} {0}",
+            format_number(width)
+        )?;
+        writeln!(writer, "%%LanguageLevel: 2")?;
+        writeln!(writer, "%%EndComments")?;
+
+        // Draw in module units with the origin at the bottom left of the quiet zone, keeping the
+        // procedure in a private dictionary so it cannot clash with the enclosing document
+        writeln!(writer, "gsave")?;
+        writeln!(writer, "{0} {0} scale", format_number(options.module_size))?;
+        writeln!(writer, "1 dict begin")?;
+        writeln!(writer, "/r {{ 1 rectfill }} bind def")?;
+
+        if let Some(light) = options.light {
+            writeln!(writer, "{}", light.set_color())?;
+            writeln!(writer, "0 0 {0} {0} rectfill", modules)?;
+        }
+
+        writeln!(writer, "{}", options.dark.set_color())?;
+        for row in 0..self.size() {
+            let y = modules - options.quiet_zone - row - 1;
+
+            for (column, length) in self.dark_runs(row) {
+                writeln!(writer, "{} {} {} r", column + options.quiet_zone, y, length)?;
+            }
+        }
+
+        writeln!(writer, "end")?;
+        writeln!(writer, "grestore")?;
+        writeln!(writer, "showpage")?;
+        writeln!(writer, "%%EOF")
+    }
+
+    /// Saves the bitmap to a file in EPS format
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn save_eps<P>(&self, path: P, options: &EpsOptions) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_eps(&mut writer, options)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_eps() {
+        let mut bit_map = BitMap::new(2);
+        bit_map.set(0, 0, 1);
+        bit_map.set(0, 1, 1);
+        bit_map.set(1, 1, 1);
+
+        let options = EpsOptions {
+            module_size: 1.5,
+            quiet_zone: 1,
+            dark: EpsColor::Cmyk {
+                c: 0.0,
+                m: -0.5,
+                y: 0.0,
+                k: 1.5,
+            },
+            light: None,
+        };
+
+        let mut eps: Vec<u8> = Vec::new();
+        bit_map.write_eps(&mut eps, &options).unwrap();
+        let eps = String::from_utf8(eps).unwrap();
+
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+        assert!(eps.contains("%%BoundingBox: 0 0 6 6\n"));
+        assert!(eps.contains("%%HiResBoundingBox: 0 0 6 6\n"));
+        assert!(eps.contains("1 dict begin\n/r { 1 rectfill } bind def\n"));
+        assert!(eps.contains("0 0 0 1 setcmykcolor\n1 2 2 r\n2 1 1 r\nend\ngrestore\n"));
+    }
+}
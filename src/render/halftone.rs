@@ -0,0 +1,148 @@
+//! # Halftone
+//!
+//! Draws a grayscale picture into a QR code. Every module becomes a 3 x 3 grid of sub-modules:
+//! the centre keeps the value of the module, which is where scanners sample it, and the outer
+//! eight follow the picture dithered with Floyd-Steinberg error diffusion. Function patterns
+//! keep all nine sub-modules so the symbol is still found and its format read.
+//!
+//! The result is a bitmap three times the size of the symbol, so the quiet zone and module
+//! size of the writers count sub-modules.
+//!
+//! ```rust,ignore
+//! let qr_code = create_halftone_qr_code("HELLO", ErrorCorrectionLevel::H, &picture);
+//! qr_code.save_png(
+//!     "halftone.png",
+//!     &RenderOptions { module_px: 2, quiet_zone_modules: 12, ..RenderOptions::default() },
+//! )?;
+//! ```
+
+use super::netpbm::GrayImage;
+use crate::bit_utils::{bit::Bit, bitmap::BitMap};
+
+/// Sub-modules per module in each direction
+pub const HALFTONE_SCALE: usize = 3;
+
+impl GrayImage {
+    /// Averages the picture over a grid of square cells, stretching it to a square
+    ///
+    /// # Arguments
+    ///
+    /// * `cells` - Number of cells in each direction
+    ///
+    /// # Returns
+    ///
+    /// The mean brightness of every cell row by row, white for an empty picture
+    pub(crate) fn resample(&self, cells: usize) -> Vec<f64> {
+        if self.width == 0 || self.height == 0 {
+            return vec![255.0; cells * cells];
+        }
+
+        // Every cell covers at least one pixel, even when the picture is smaller than the grid
+        let span = |cell: usize, length: usize| {
+            let start = cell * length / cells;
+            start..((cell + 1) * length / cells).max(start + 1)
+        };
+
+        let mut values = Vec::with_capacity(cells * cells);
+
+        for row in 0..cells {
+            for column in 0..cells {
+                let (rows, columns) = (span(row, self.height), span(column, self.width));
+                let count = (rows.len() * columns.len()) as f64;
+
+                let sum: f64 = rows
+                    .flat_map(|y| columns.clone().map(move |x| (x, y)))
+                    .map(|(x, y)| self.get(x, y) as f64)
+                    .sum();
+
+                values.push(sum / count);
+            }
+        }
+
+        values
+    }
+}
+
+impl BitMap {
+    /// Draws a picture into the modules with 3 x 3 sub-modules each
+    ///
+    /// # Arguments
+    ///
+    /// * `picture` - Grayscale picture stretched over the symbol
+    ///
+    /// # Returns
+    ///
+    /// A bitmap of sub-modules, three times the size of the symbol
+    pub fn halftone(&self, picture: &GrayImage) -> BitMap {
+        let size = self.size() * HALFTONE_SCALE;
+        let mut values = picture.resample(size);
+        let mut halftone = BitMap::new(size);
+
+        for y in 0..size {
+            for x in 0..size {
+                let (row, column) = (y / HALFTONE_SCALE, x / HALFTONE_SCALE);
+                let module_dark = self.get(row, column) == Bit::One;
+
+                // Function patterns are copied as they are and take no part in the diffusion
+                if self.role(row, column).is_function_pattern() {
+                    halftone.set(y, x, module_dark as u8);
+                    continue;
+                }
+
+                let centre = y % HALFTONE_SCALE == 1 && x % HALFTONE_SCALE == 1;
+                let value = values[y * size + x];
+                let dark = if centre { module_dark } else { value < 128.0 };
+
+                halftone.set(y, x, dark as u8);
+
+                let error = value - if dark { 0.0 } else { 255.0 };
+                let mut spread = |dx: isize, dy: usize, weight: f64| {
+                    let target_x = x as isize + dx;
+
+                    if target_x >= 0 && (target_x as usize) < size && y + dy < size {
+                        values[(y + dy) * size + target_x as usize] += error * weight / 16.0;
+                    }
+                };
+
+                spread(1, 0, 7.0);
+                spread(-1, 1, 3.0);
+                spread(0, 1, 5.0);
+                spread(1, 1, 1.0);
+            }
+        }
+
+        halftone
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_utils::bitmap::ModuleRole;
+
+    #[test]
+    fn test_halftone() {
+        let picture = GrayImage {
+            width: 2,
+            height: 1,
+            pixels: vec![0, 255],
+        };
+        assert_eq!(picture.resample(2), vec![0.0, 255.0, 0.0, 255.0]);
+
+        let mut bit_map = BitMap::new(2);
+        bit_map.set(0, 1, 1);
+        bit_map.set_role(1, 0, ModuleRole::Timing);
+
+        let halftone = bit_map.halftone(&picture);
+        assert_eq!(halftone.size(), 6);
+
+        // The centres keep the modules against the picture, the rest follows the picture
+        assert_eq!(halftone.get(1, 1), Bit::Zero);
+        assert_eq!(halftone.get(0, 0), Bit::One);
+        assert_eq!(halftone.get(1, 4), Bit::One);
+        assert_eq!(halftone.get(0, 4), Bit::Zero);
+
+        // The light function pattern module stays light under the black half of the picture
+        assert!((3..6).all(|y| (0..3).all(|x| halftone.get(y, x) == Bit::Zero)));
+    }
+}
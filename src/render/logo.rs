@@ -0,0 +1,415 @@
+//! # Logo
+//!
+//! Draws a logo over the area cleared by `create_logo_qr_code`. The logo is scaled to fit the
+//! cleared modules, keeping its aspect ratio, and centred in them. Raster logos are
+//! alpha-blended into PNG images and embedded as PNG data in SVG images, SVG fragments can only
+//! be drawn in SVG images.
+//!
+//! ```rust,ignore
+//! let qr_code = create_logo_qr_code("https://example.com", ErrorCorrectionLevel::M, &options)?;
+//! let logo = LogoImage::Svg {
+//!     markup: r#"<circle cx="50" cy="50" r="50" fill="red"/>"#.to_string(),
+//!     width: 100.0,
+//!     height: 100.0,
+//! };
+//!
+//! qr_code.save_svg_with_logo("qr_code.svg", &SvgOptions::default(), &logo)?;
+//! ```
+
+use super::png::write_rgba_png;
+use super::svg::SvgOptions;
+use super::{base64_encode, format_number, RenderOptions};
+use crate::bit_utils::bitmap::{BitMap, ModuleRole};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Image drawn over the logo area of a QR code
+#[derive(PartialEq, Debug, Clone)]
+pub enum LogoImage {
+    /// 8-bit RGBA pixels from the top left, four bytes per pixel
+    Rgba {
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    },
+    /// SVG elements drawn in a `viewBox` of the given size
+    Svg {
+        markup: String,
+        width: f64,
+        height: f64,
+    },
+}
+
+impl LogoImage {
+    /// Width and height of the image in its own units
+    fn size(&self) -> (f64, f64) {
+        match self {
+            LogoImage::Rgba { width, height, .. } => (*width as f64, *height as f64),
+            LogoImage::Svg { width, height, .. } => (*width, *height),
+        }
+    }
+
+    /// Fits the image in a box, keeping its aspect ratio and centring it
+    ///
+    /// # Returns
+    ///
+    /// The left, top, width and height of the image
+    fn fit(&self, x: f64, y: f64, width: f64, height: f64) -> (f64, f64, f64, f64) {
+        let (logo_width, logo_height) = self.size();
+        let scale = (width / logo_width).min(height / logo_height);
+        let (fitted_width, fitted_height) = (logo_width * scale, logo_height * scale);
+
+        (
+            x + (width - fitted_width) / 2.0,
+            y + (height - fitted_height) / 2.0,
+            fitted_width,
+            fitted_height,
+        )
+    }
+
+    /// Checks that the image has a size and, for raster images, all its pixels
+    fn validate(&self) -> io::Result<()> {
+        let valid = match self {
+            LogoImage::Rgba {
+                width,
+                height,
+                pixels,
+            } => {
+                *width > 0
+                    && *height > 0
+                    && width
+                        .checked_mul(*height)
+                        .and_then(|size| size.checked_mul(4))
+                        .is_some_and(|size| pixels.len() == size)
+            }
+            LogoImage::Svg { width, height, .. } => *width > 0.0 && *height > 0.0,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "logo has no size or the wrong number of pixels",
+            ))
+        }
+    }
+}
+
+/// Averages the pixels of an RGBA image under an area, with the colours premultiplied by alpha
+///
+/// # Returns
+///
+/// The red, green and blue values premultiplied by the alpha and the alpha between 0 and 1
+fn average_pixels(
+    width: usize,
+    pixels: &[u8],
+    (x0, y0): (usize, usize),
+    (x1, y1): (usize, usize),
+) -> [f64; 4] {
+    let mut sum = [0.0; 4];
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let pixel = &pixels[(y * width + x) * 4..(y * width + x) * 4 + 4];
+            let alpha = pixel[3] as f64 / 255.0;
+
+            for channel in 0..3 {
+                sum[channel] += pixel[channel] as f64 * alpha;
+            }
+            sum[3] += alpha;
+        }
+    }
+
+    let count = ((x1 - x0) * (y1 - y0)).max(1) as f64;
+    sum.map(|value| value / count)
+}
+
+impl BitMap {
+    /// Returns the area of the modules cleared for a logo
+    ///
+    /// # Returns
+    ///
+    /// The row and column of the top left module and the height and width in modules, or
+    /// `None` if no modules have the logo role
+    pub fn logo_area(&self) -> Option<(usize, usize, usize, usize)> {
+        let mut area: Option<(usize, usize, usize, usize)> = None;
+
+        for row in 0..self.size() {
+            for column in 0..self.size() {
+                if self.role(row, column) != ModuleRole::Logo {
+                    continue;
+                }
+
+                let (top, left, bottom, right) = area.unwrap_or((row, column, row, column));
+                area = Some((
+                    top.min(row),
+                    left.min(column),
+                    bottom.max(row),
+                    right.max(column),
+                ));
+            }
+        }
+
+        area.map(|(top, left, bottom, right)| (top, left, bottom - top + 1, right - left + 1))
+    }
+
+    /// Returns the logo area or an error for bitmaps without one
+    fn require_logo_area(&self) -> io::Result<(usize, usize, usize, usize)> {
+        self.logo_area().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "QR code has no area cleared for a logo",
+            )
+        })
+    }
+
+    /// Writes the bitmap as a PNG image with a raster logo blended over the logo area
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Module size, quiet zone and colours of the image
+    /// * `logo` - The logo, which must be a raster image
+    pub fn write_png_with_logo<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+        logo: &LogoImage,
+    ) -> io::Result<()> {
+        logo.validate()?;
+        let (row, column, height, width) = self.require_logo_area()?;
+
+        let LogoImage::Rgba {
+            width: logo_width,
+            height: logo_height,
+            pixels: logo_pixels,
+        } = logo
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "SVG logos can only be drawn in SVG images",
+            ));
+        };
+
+        let (image_width, image_height, mut pixels) = self.to_rgba8(options);
+
+        let scale = options.scale() as f64;
+        let quiet_zone = options.quiet_zone_modules as f64;
+        let (x, y, fitted_width, fitted_height) = logo.fit(
+            (column as f64 + quiet_zone) * scale,
+            (row as f64 + quiet_zone) * scale,
+            width as f64 * scale,
+            height as f64 * scale,
+        );
+
+        let (x0, y0) = (x.round() as usize, y.round() as usize);
+        let (x1, y1) = (
+            ((x + fitted_width).round() as usize).min(image_width),
+            ((y + fitted_height).round() as usize).min(image_height),
+        );
+
+        // Every image pixel takes the average of the logo pixels it covers
+        let source = |position: usize, start: usize, end: usize, length: usize| {
+            (position - start) * length / (end - start).max(1)
+        };
+
+        for image_y in y0..y1 {
+            let top = source(image_y, y0, y1, *logo_height);
+            let bottom = source(image_y + 1, y0, y1, *logo_height).max(top + 1);
+
+            for image_x in x0..x1 {
+                let left = source(image_x, x0, x1, *logo_width);
+                let right = source(image_x + 1, x0, x1, *logo_width).max(left + 1);
+
+                let [r, g, b, alpha] =
+                    average_pixels(*logo_width, logo_pixels, (left, top), (right, bottom));
+                let pixel = &mut pixels[(image_y * image_width + image_x) * 4..][..3];
+
+                for (channel, value) in pixel.iter_mut().zip([r, g, b]) {
+                    *channel = (value + *channel as f64 * (1.0 - alpha)).round() as u8;
+                }
+            }
+        }
+
+        write_rgba_png(writer, image_width, image_height, &pixels, None)
+    }
+
+    /// Saves the bitmap to a file in PNG format with a raster logo over the logo area
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    /// * `logo` - The logo, which must be a raster image
+    pub fn save_png_with_logo<P>(
+        &self,
+        path: P,
+        options: &RenderOptions,
+        logo: &LogoImage,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png_with_logo(&mut writer, options, logo)?;
+        writer.flush()
+    }
+
+    /// Writes the bitmap as an SVG image with a logo over the logo area
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Scale, quiet zone and colours of the image
+    /// * `logo` - The logo, raster images are embedded as PNG data
+    pub fn write_svg_with_logo<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &SvgOptions,
+        logo: &LogoImage,
+    ) -> io::Result<()> {
+        logo.validate()?;
+        let (row, column, height, width) = self.require_logo_area()?;
+
+        let (x, y, fitted_width, fitted_height) = logo.fit(
+            (column + options.quiet_zone) as f64,
+            (row + options.quiet_zone) as f64,
+            width as f64,
+            height as f64,
+        );
+        let n = format_number;
+
+        self.write_svg_elements(writer, options, None)?;
+
+        match logo {
+            LogoImage::Rgba {
+                width,
+                height,
+                pixels,
+            } => {
+                let mut png: Vec<u8> = Vec::new();
+                write_rgba_png(&mut png, *width, *height, pixels, None)?;
+
+                writeln!(
+                    writer,
+                    r#"<image x="{}" y="{}" width="{}" height="{}" href="data:image/png;base64,{}"/>"#,
+                    n(x),
+                    n(y),
+                    n(fitted_width),
+                    n(fitted_height),
+                    base64_encode(&png)
+                )?;
+            }
+            LogoImage::Svg {
+                markup,
+                width,
+                height,
+            } => {
+                writeln!(
+                    writer,
+                    r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="0 0 {} {}">{}</svg>"#,
+                    n(x),
+                    n(y),
+                    n(fitted_width),
+                    n(fitted_height),
+                    n(*width),
+                    n(*height),
+                    markup
+                )?;
+            }
+        }
+
+        writeln!(writer, "</svg>")
+    }
+
+    /// Saves the bitmap to a file in SVG format with a logo over the logo area
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Scale, quiet zone and colours of the image
+    /// * `logo` - The logo, raster images are embedded as PNG data
+    pub fn save_svg_with_logo<P>(
+        &self,
+        path: P,
+        options: &SvgOptions,
+        logo: &LogoImage,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_svg_with_logo(&mut writer, options, logo)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_logo_qr_code, ErrorCorrectionLevel, LogoOptions, LogoShape};
+
+    #[test]
+    fn test_logo_images() {
+        let options = LogoOptions {
+            shape: LogoShape::Rectangle {
+                width: 5,
+                height: 5,
+            },
+            ..LogoOptions::default()
+        };
+        let qr_code = create_logo_qr_code("HELLO", ErrorCorrectionLevel::L, &options).unwrap();
+        // Version 1 would lose too many codewords
+        assert_eq!(qr_code.size(), 25);
+        assert_eq!(qr_code.logo_area(), Some((10, 10, 5, 5)));
+
+        // A 1 x 2 red logo fits the 5 x 5 area as 5 x 2.5 modules
+        let logo = LogoImage::Rgba {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 0, 0, 255, 255, 0, 0, 128],
+        };
+
+        let mut svg: Vec<u8> = Vec::new();
+        qr_code
+            .write_svg_with_logo(&mut svg, &SvgOptions::default(), &logo)
+            .unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains(
+            r#"<image x="14" y="15.25" width="5" height="2.5" href="data:image/png;base64,"#
+        ));
+        assert!(svg.ends_with("/>\n</svg>\n"));
+
+        let render_options = RenderOptions {
+            module_px: 2,
+            quiet_zone_modules: 0,
+            ..RenderOptions::default()
+        };
+        let mut png: Vec<u8> = Vec::new();
+        qr_code
+            .write_png_with_logo(&mut png, &render_options, &logo)
+            .unwrap();
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..26], [0, 0, 0, 50, 0, 0, 0, 50, 8, 6]);
+
+        let svg_logo = LogoImage::Svg {
+            markup: String::new(),
+            width: 1.0,
+            height: 1.0,
+        };
+        assert!(qr_code
+            .write_png_with_logo(&mut Vec::new(), &render_options, &svg_logo)
+            .is_err());
+
+        // A size whose pixel count overflows is reported instead of panicking
+        let huge_logo = LogoImage::Rgba {
+            width: usize::MAX / 2,
+            height: 3,
+            pixels: vec![0; 8],
+        };
+        assert!(qr_code
+            .write_svg_with_logo(&mut Vec::new(), &SvgOptions::default(), &huge_logo)
+            .is_err());
+    }
+}
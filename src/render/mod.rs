@@ -0,0 +1,400 @@
+//! # Rendering
+//!
+//! Image writers for finished QR codes. Every format is added as methods on `BitMap`, where a
+//! set bit is a dark module.
+
+pub mod ansi;
+pub mod bmp;
+pub mod caption;
+pub mod deflate;
+pub mod eps;
+pub mod font;
+pub mod halftone;
+pub mod logo;
+pub mod netpbm;
+pub mod paint;
+pub mod pdf;
+pub mod png;
+pub mod raster;
+pub mod sixel;
+pub mod sizing;
+pub mod style;
+pub mod svg;
+pub mod terminal;
+pub mod text;
+
+use crate::bit_utils::{bit::Bit, bitmap::BitMap};
+use paint::{ContrastWarning, Region, RegionColors};
+use std::fmt::Display;
+use std::io::{self, Write};
+use style::ModuleStyle;
+
+/// An RGB colour
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    /// Creates a colour from its red, green and blue components
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Returns the brightness of the colour with the ITU-R BT.601 weights
+    pub fn luma(&self) -> u8 {
+        ((self.r as u32 * 299 + self.g as u32 * 587 + self.b as u32 * 114 + 500) / 1000) as u8
+    }
+
+    /// Returns the relative luminance of the colour as defined by WCAG, between 0 and 1
+    pub fn relative_luminance(&self) -> f64 {
+        let linear = |channel: u8| {
+            let value = channel as f64 / 255.0;
+
+            if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// Returns the WCAG contrast ratio of the colour to another one
+    ///
+    /// # Returns
+    ///
+    /// A ratio from 1 to 21 when this colour is the lighter one, below 1 otherwise
+    pub fn contrast_ratio(&self, other: Color) -> f64 {
+        (self.relative_luminance() + 0.05) / (other.relative_luminance() + 0.05)
+    }
+
+    /// Blends the colour with another one
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The colour to blend in
+    /// * `t` - Share of the other colour, clamped between 0 and 1
+    pub fn mix(&self, other: Color, t: f64) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+
+        Color::rgb(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+        )
+    }
+}
+
+/// Formats the colour as a hexadecimal `#rrggbb` string
+impl Display for Color {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Options shared by the raster writers
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// Size of a module in pixels, 0 is treated as 1
+    pub module_px: usize,
+    /// Width of the quiet zone around the symbol in modules
+    pub quiet_zone_modules: usize,
+    /// Colour of the dark modules
+    pub dark: Color,
+    /// Colour of the light modules and the quiet zone
+    pub light: Color,
+    /// Shapes of the modules and finder patterns
+    pub style: ModuleStyle,
+    /// Colours and gradients of the regions of the symbol, taking the place of the dark colour
+    pub colors: RegionColors,
+}
+
+impl Default for RenderOptions {
+    /// 4 pixels per module, black on white, with the 4 module quiet zone recommended by ISO/IEC
+    /// 18004
+    fn default() -> Self {
+        Self {
+            module_px: 4,
+            quiet_zone_modules: 4,
+            dark: Color::BLACK,
+            light: Color::WHITE,
+            style: ModuleStyle::default(),
+            colors: RegionColors::default(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Size of a module in pixels, at least 1
+    pub fn scale(&self) -> usize {
+        self.module_px.max(1)
+    }
+
+    /// Width and height of the image of a bitmap in pixels
+    pub fn image_size(&self, bit_map: &BitMap) -> usize {
+        (bit_map.size() + 2 * self.quiet_zone_modules) * self.scale()
+    }
+
+    /// Checks the colours of the dark modules against the light colour
+    ///
+    /// # Returns
+    ///
+    /// A warning for every colour scanners may not tell apart from the light colour
+    pub fn contrast_warnings(&self) -> Vec<ContrastWarning> {
+        self.colors.contrast_warnings(self.dark, self.light)
+    }
+}
+
+/// An output format that draws a module matrix
+///
+/// Implement this for in-house formats, such as printer languages, and render with
+/// `BitMap::render`. The options types of the built-in formats are renderers as well.
+///
+/// ```rust,ignore
+/// struct Ascii;
+///
+/// impl Renderer for Ascii {
+///     fn render(
+///         &self,
+///         bit_map: &BitMap,
+///         writer: &mut dyn Write,
+///         options: &RenderOptions,
+///     ) -> io::Result<()> {
+///         let size = options.image_size(bit_map);
+///
+///         for y in (0..size).step_by(options.scale()) {
+///             let line: String = (0..size)
+///                 .step_by(options.scale())
+///                 .map(|x| if bit_map.is_dark_pixel(x, y, options) { '#' } else { ' ' })
+///                 .collect();
+///             writeln!(writer, "{}", line)?;
+///         }
+///
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait Renderer {
+    /// Writes the bitmap in the output format
+    ///
+    /// # Arguments
+    ///
+    /// * `bit_map` - The modules of the QR code, a set bit is a dark module
+    /// * `writer` - Destination of the output
+    /// * `options` - Module size, quiet zone and colours of the output
+    fn render(
+        &self,
+        bit_map: &BitMap,
+        writer: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> io::Result<()>;
+}
+
+/// Formats a length for PostScript and PDF, with at most 4 decimals and no trailing zeros
+pub(crate) fn format_number(value: f64) -> String {
+    let formatted = format!("{:.4}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    match trimmed {
+        "" | "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Encodes data in standard base64 with padding
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(group >> (18 - 6 * index)) as usize & 0x3F] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+impl BitMap {
+    /// Writes the bitmap with a renderer
+    ///
+    /// # Arguments
+    ///
+    /// * `renderer` - The output format
+    /// * `writer` - Destination of the output
+    /// * `options` - Module size, quiet zone and colours of the output
+    pub fn render<R: Renderer + ?Sized>(
+        &self,
+        renderer: &R,
+        writer: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        renderer.render(self, writer, options)
+    }
+
+    /// Checks whether a module is dark, with the symbol surrounded by a quiet zone
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row index, counted from the top of the quiet zone
+    /// * `column` - Column index, counted from the left of the quiet zone
+    /// * `quiet_zone` - Width of the quiet zone in modules
+    ///
+    /// # Returns
+    ///
+    /// `true` for dark modules, modules in the quiet zone are always light
+    pub(crate) fn is_dark(&self, row: usize, column: usize, quiet_zone: usize) -> bool {
+        let range = quiet_zone..quiet_zone + self.size();
+
+        range.contains(&row)
+            && range.contains(&column)
+            && self.get(row - quiet_zone, column - quiet_zone) == Bit::One
+    }
+
+    /// Checks whether a pixel of a raster image of the bitmap is dark
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Pixel column, counted from the left of the image
+    /// * `y` - Pixel row, counted from the top of the image
+    /// * `options` - Module size and quiet zone of the image
+    pub fn is_dark_pixel(&self, x: usize, y: usize, options: &RenderOptions) -> bool {
+        let scale = options.scale();
+
+        if options.style == ModuleStyle::default() {
+            return self.is_dark(y / scale, x / scale, options.quiet_zone_modules);
+        }
+
+        // Styled shapes are sampled at the centre of the pixel
+        let quiet_zone = options.quiet_zone_modules as f64;
+        let position = |pixel: usize| (pixel as f64 + 0.5) / scale as f64 - quiet_zone;
+
+        self.is_dark_styled(position(x), position(y), &options.style)
+    }
+
+    /// Returns the colour of a pixel of a raster image of the bitmap
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Pixel column, counted from the left of the image
+    /// * `y` - Pixel row, counted from the top of the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn pixel_color(&self, x: usize, y: usize, options: &RenderOptions) -> Color {
+        if !self.is_dark_pixel(x, y, options) {
+            return options.light;
+        }
+
+        if options.colors == RegionColors::default() {
+            return options.dark;
+        }
+
+        // Gradients are sampled at the centre of the pixel, relative to the symbol
+        let scale = options.scale() as f64;
+        let quiet_zone = options.quiet_zone_modules as f64;
+        let position = |pixel: usize| (pixel as f64 + 0.5) / scale - quiet_zone;
+        let (column, row) = (position(x), position(y));
+
+        let role = self.role(row.max(0.0) as usize, column.max(0.0) as usize);
+        let size = self.size() as f64;
+
+        options
+            .colors
+            .paint(Region::of(role), options.dark)
+            .color_at(column / size, row / size)
+    }
+
+    /// Finds the horizontal runs of dark modules in a row
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row index
+    ///
+    /// # Returns
+    ///
+    /// The starting column and length of every run
+    pub(crate) fn dark_runs(&self, row: usize) -> Vec<(usize, usize)> {
+        self.dark_runs_where(row, |_| true)
+    }
+
+    /// Finds the horizontal runs of dark modules of a region in a row
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row index
+    /// * `region` - Region of the symbol the modules belong to
+    ///
+    /// # Returns
+    ///
+    /// The starting column and length of every run
+    pub(crate) fn region_dark_runs(&self, row: usize, region: Region) -> Vec<(usize, usize)> {
+        self.dark_runs_where(row, |column| Region::of(self.role(row, column)) == region)
+    }
+
+    /// Finds the horizontal runs of dark modules in a row that pass a filter on their column
+    fn dark_runs_where(&self, row: usize, include: impl Fn(usize) -> bool) -> Vec<(usize, usize)> {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut start: Option<usize> = None;
+
+        for column in 0..=self.size() {
+            let dark = column < self.size() && self.get(row, column) == Bit::One && include(column);
+
+            match (dark, start) {
+                (true, None) => start = Some(column),
+                (false, Some(first)) => {
+                    runs.push((first, column - first));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_options() {
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, 1);
+
+        let options = RenderOptions::default();
+        assert_eq!(options.image_size(&bit_map), 36);
+
+        // Pixels 16 to 19 of both axes cover the only module
+        assert!(!bit_map.is_dark_pixel(15, 16, &options));
+        assert!(bit_map.is_dark_pixel(16, 19, &options));
+        assert!(!bit_map.is_dark_pixel(20, 19, &options));
+
+        let options = RenderOptions {
+            module_px: 0,
+            quiet_zone_modules: 0,
+            ..options
+        };
+        assert_eq!(options.image_size(&bit_map), 1);
+        assert!(bit_map.is_dark_pixel(0, 0, &options));
+    }
+}
@@ -0,0 +1,490 @@
+//! # Netpbm
+//!
+//! Writers for the Netpbm formats: PBM (P1, P4), PGM (P2, P5) and PPM (P6), and readers that
+//! load PBM and PGM images back into a `BitMap` or a grayscale buffer. The formats are trivial to
+//! pipe through ImageMagick or the netpbm utilities.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! let options = RenderOptions::default();
+//! qr_code.save_netpbm("qr_code.pbm", NetpbmFormat::PbmBinary, &options)?;
+//!
+//! let read = BitMap::read_netpbm(File::open("qr_code.pbm")?, &options)?;
+//! ```
+
+use super::RenderOptions;
+use crate::bit_utils::bitmap::BitMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+/// Netpbm image format
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum NetpbmFormat {
+    /// P1, black and white pixels as ASCII digits
+    PbmAscii,
+    /// P4, black and white pixels packed 8 per byte
+    PbmBinary,
+    /// P2, gray pixels as ASCII numbers
+    PgmAscii,
+    /// P5, gray pixels as bytes
+    PgmBinary,
+    /// P6, RGB pixels as bytes
+    PpmBinary,
+}
+
+/// Errors that can occur while reading Netpbm images
+#[derive(Debug)]
+pub enum NetpbmError {
+    Io(io::Error),
+    /// The magic number is not one of P1, P2, P4 or P5
+    UnsupportedFormat,
+    /// The header is missing or malformed
+    InvalidHeader,
+    /// The pixel data is truncated or has invalid values
+    InvalidData,
+    /// The image size does not fit the scale and quiet zone of a square QR code
+    InvalidSize {
+        width: usize,
+        height: usize,
+    },
+}
+
+impl Display for NetpbmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            NetpbmError::Io(error) => write!(f, "{}", error),
+            NetpbmError::UnsupportedFormat => write!(f, "unsupported netpbm format"),
+            NetpbmError::InvalidHeader => write!(f, "invalid netpbm header"),
+            NetpbmError::InvalidData => write!(f, "invalid or truncated pixel data"),
+            NetpbmError::InvalidSize { width, height } => {
+                write!(f, "image size {}x{} does not fit a QR code", width, height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetpbmError {}
+
+impl From<io::Error> for NetpbmError {
+    fn from(error: io::Error) -> Self {
+        NetpbmError::Io(error)
+    }
+}
+
+/// An 8-bit grayscale image
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels row by row from the top left, 0 is black and 255 is white
+    pub pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// Returns the pixel at the column `x` and row `y`
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Thresholds the image of a QR code into a bitmap
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours the image was written with
+    ///
+    /// # Returns
+    ///
+    /// The bitmap with the pixel at the centre of every module thresholded halfway between the
+    /// luma of the colours, or `None` if the image does not fit the module size and quiet zone
+    pub(crate) fn to_bit_map(&self, options: &RenderOptions) -> Option<BitMap> {
+        let scale = options.scale();
+        let quiet_zone = options.quiet_zone_modules;
+        let modules = self.width / scale;
+
+        if self.width != self.height
+            || !self.width.is_multiple_of(scale)
+            || modules <= 2 * quiet_zone
+        {
+            return None;
+        }
+
+        let (dark, light) = (options.dark.luma() as i32, options.light.luma() as i32);
+        let size = modules - 2 * quiet_zone;
+        let mut bit_map = BitMap::new(size);
+
+        for i in 0..size {
+            for j in 0..size {
+                let y = (i + quiet_zone) * scale + scale / 2;
+                let x = (j + quiet_zone) * scale + scale / 2;
+                let pixel = self.get(x, y) as i32;
+                bit_map.set(i, j, ((pixel - dark).abs() < (pixel - light).abs()) as u8);
+            }
+        }
+
+        Some(bit_map)
+    }
+
+    /// Reads a PBM (P1, P4) or PGM (P2, P5) image
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the image data
+    ///
+    /// # Returns
+    ///
+    /// The image with its pixels scaled to 0 - 255, or a `NetpbmError` if it cannot be read
+    pub fn read_netpbm<R: Read>(mut reader: R) -> Result<GrayImage, NetpbmError> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let mut parser = HeaderParser {
+            data: &data,
+            index: 2,
+        };
+        let magic = data.get(..2).ok_or(NetpbmError::InvalidHeader)?;
+        let pbm = matches!(magic, b"P1" | b"P4");
+
+        if !matches!(magic, b"P1" | b"P2" | b"P4" | b"P5") {
+            return Err(NetpbmError::UnsupportedFormat);
+        }
+
+        let width = parser.number()?;
+        let height = parser.number()?;
+        let max_value = if pbm { 1 } else { parser.number()? };
+
+        if max_value == 0 || max_value > 65535 {
+            return Err(NetpbmError::InvalidHeader);
+        }
+
+        let scale = |value: usize| -> Result<u8, NetpbmError> {
+            match (pbm, value <= max_value) {
+                // 1 is black in PBM images
+                (true, true) => Ok(if value == 1 { 0 } else { 255 }),
+                (false, true) => Ok((value * 255 / max_value) as u8),
+                _ => Err(NetpbmError::InvalidData),
+            }
+        };
+
+        // Every pixel takes at least one byte, or one bit in P4 images, so a header claiming more
+        // pixels than the data can hold is rejected before allocating
+        let pixel_count = width.checked_mul(height).ok_or(NetpbmError::InvalidData)?;
+        let minimum_size = match magic {
+            b"P4" => width.div_ceil(8).checked_mul(height),
+            _ => Some(pixel_count),
+        };
+
+        if minimum_size.is_none_or(|size| data.len() - parser.index < size) {
+            return Err(NetpbmError::InvalidData);
+        }
+
+        let mut pixels: Vec<u8> = Vec::with_capacity(pixel_count);
+
+        match magic {
+            b"P1" => {
+                // Digits may be written without whitespace between them
+                for _ in 0..pixel_count {
+                    parser.skip_whitespace();
+                    let digit = parser
+                        .data
+                        .get(parser.index)
+                        .ok_or(NetpbmError::InvalidData)?;
+                    pixels.push(scale(digit.wrapping_sub(b'0') as usize)?);
+                    parser.index += 1;
+                }
+            }
+            b"P2" => {
+                for _ in 0..pixel_count {
+                    let value = parser.number().map_err(|_| NetpbmError::InvalidData)?;
+                    pixels.push(scale(value)?);
+                }
+            }
+            b"P4" => {
+                // A single whitespace character separates the header from the pixel data
+                let raster = data
+                    .get(parser.index + 1..)
+                    .ok_or(NetpbmError::InvalidData)?;
+                let row_bytes = width.div_ceil(8);
+
+                if row_bytes
+                    .checked_mul(height)
+                    .is_none_or(|size| raster.len() < size)
+                {
+                    return Err(NetpbmError::InvalidData);
+                }
+
+                for y in 0..height {
+                    for x in 0..width {
+                        let byte = raster[y * row_bytes + x / 8];
+                        pixels.push(scale(((byte >> (7 - x % 8)) & 1) as usize)?);
+                    }
+                }
+            }
+            _ => {
+                let raster = data
+                    .get(parser.index + 1..)
+                    .ok_or(NetpbmError::InvalidData)?;
+                let sample_bytes = if max_value < 256 { 1 } else { 2 };
+
+                if pixel_count
+                    .checked_mul(sample_bytes)
+                    .is_none_or(|size| raster.len() < size)
+                {
+                    return Err(NetpbmError::InvalidData);
+                }
+
+                for sample in raster.chunks(sample_bytes).take(pixel_count) {
+                    let value = sample
+                        .iter()
+                        .fold(0usize, |value, byte| (value << 8) | *byte as usize);
+                    pixels.push(scale(value)?);
+                }
+            }
+        }
+
+        Ok(GrayImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Reads the whitespace separated numbers of a Netpbm header, skipping comments
+struct HeaderParser<'a> {
+    data: &'a [u8],
+    index: usize,
+}
+
+impl HeaderParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.data.get(self.index) {
+            match byte {
+                b'#' => {
+                    while self.data.get(self.index).is_some_and(|byte| *byte != b'\n') {
+                        self.index += 1;
+                    }
+                }
+                b' ' | b'\t' | b'\n' | b'\r' => self.index += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, NetpbmError> {
+        self.skip_whitespace();
+
+        let start = self.index;
+        while self.data.get(self.index).is_some_and(u8::is_ascii_digit) {
+            self.index += 1;
+        }
+
+        std::str::from_utf8(&self.data[start..self.index])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or(NetpbmError::InvalidHeader)
+    }
+}
+
+impl BitMap {
+    /// Writes the bitmap as a Netpbm image
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `format` - Netpbm format of the image
+    /// * `options` - Module size, quiet zone and colours of the image, PGM images use the luma of
+    ///   the colours and PBM images ignore them
+    pub fn write_netpbm<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        format: NetpbmFormat,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let width = options.image_size(self);
+
+        let (magic, max_value) = match format {
+            NetpbmFormat::PbmAscii => ("P1", None),
+            NetpbmFormat::PbmBinary => ("P4", None),
+            NetpbmFormat::PgmAscii => ("P2", Some(255)),
+            NetpbmFormat::PgmBinary => ("P5", Some(255)),
+            NetpbmFormat::PpmBinary => ("P6", Some(255)),
+        };
+
+        write!(writer, "{}\n{} {}\n", magic, width, width)?;
+        if let Some(max_value) = max_value {
+            writeln!(writer, "{}", max_value)?;
+        }
+
+        for y in 0..width {
+            let dark = |x: usize| self.is_dark_pixel(x, y, options);
+
+            let line: Vec<u8> = match format {
+                NetpbmFormat::PbmAscii => {
+                    let digits: Vec<&str> = (0..width)
+                        .map(|x| if dark(x) { "1" } else { "0" })
+                        .collect();
+                    format!("{}\n", digits.join(" ")).into_bytes()
+                }
+                NetpbmFormat::PbmBinary => {
+                    let mut bytes = vec![0u8; width.div_ceil(8)];
+                    for x in (0..width).filter(|x| dark(*x)) {
+                        bytes[x / 8] |= 0x80 >> (x % 8);
+                    }
+                    bytes
+                }
+                NetpbmFormat::PgmAscii => {
+                    let values: Vec<String> = (0..width)
+                        .map(|x| self.pixel_color(x, y, options).luma().to_string())
+                        .collect();
+                    format!("{}\n", values.join(" ")).into_bytes()
+                }
+                NetpbmFormat::PgmBinary => (0..width)
+                    .map(|x| self.pixel_color(x, y, options).luma())
+                    .collect(),
+                NetpbmFormat::PpmBinary => (0..width)
+                    .flat_map(|x| {
+                        let color = self.pixel_color(x, y, options);
+                        [color.r, color.g, color.b]
+                    })
+                    .collect(),
+            };
+
+            writer.write_all(&line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the bitmap to a file in a Netpbm format
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `format` - Netpbm format of the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn save_netpbm<P>(
+        &self,
+        path: P,
+        format: NetpbmFormat,
+        options: &RenderOptions,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_netpbm(&mut writer, format, options)?;
+        writer.flush()
+    }
+
+    /// Reads a PBM or PGM image of a QR code back into a bitmap
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the image data
+    /// * `options` - Module size, quiet zone and colours the image was written with
+    ///
+    /// # Returns
+    ///
+    /// The bitmap with the pixel at the centre of every module thresholded halfway between the
+    /// luma of the colours, or a `NetpbmError` if the image cannot be read or does not fit the
+    /// module size and quiet zone
+    pub fn read_netpbm<R: Read>(reader: R, options: &RenderOptions) -> Result<BitMap, NetpbmError> {
+        let image = GrayImage::read_netpbm(reader)?;
+
+        image.to_bit_map(options).ok_or(NetpbmError::InvalidSize {
+            width: image.width,
+            height: image.height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Color;
+
+    #[test]
+    fn test_write_netpbm() {
+        let mut bit_map = BitMap::new(2);
+        bit_map.set(0, 0, 1);
+        bit_map.set(1, 1, 1);
+
+        let mut options = RenderOptions {
+            module_px: 1,
+            quiet_zone_modules: 1,
+            ..RenderOptions::default()
+        };
+
+        let mut pbm: Vec<u8> = Vec::new();
+        bit_map
+            .write_netpbm(&mut pbm, NetpbmFormat::PbmAscii, &options)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(pbm).unwrap(),
+            "P1\n4 4\n0 0 0 0\n0 1 0 0\n0 0 1 0\n0 0 0 0\n"
+        );
+
+        options.dark = Color::rgb(1, 2, 3);
+
+        let mut ppm: Vec<u8> = Vec::new();
+        bit_map
+            .write_netpbm(&mut ppm, NetpbmFormat::PpmBinary, &options)
+            .unwrap();
+        assert!(ppm.starts_with(b"P6\n4 4\n255\n"));
+        assert_eq!(ppm.len(), 11 + 4 * 4 * 3);
+        assert_eq!(ppm[11 + 5 * 3..11 + 6 * 3], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_read_netpbm() {
+        let mut bit_map = BitMap::new(5);
+        for (i, j) in [(0, 0), (1, 3), (4, 4), (2, 1)] {
+            bit_map.set(i, j, 1);
+        }
+
+        for format in [
+            NetpbmFormat::PbmAscii,
+            NetpbmFormat::PbmBinary,
+            NetpbmFormat::PgmAscii,
+            NetpbmFormat::PgmBinary,
+        ] {
+            let options = RenderOptions {
+                module_px: 3,
+                quiet_zone_modules: 2,
+                dark: Color::rgb(0, 0, 128),
+                ..RenderOptions::default()
+            };
+
+            let mut image: Vec<u8> = Vec::new();
+            bit_map.write_netpbm(&mut image, format, &options).unwrap();
+
+            let read = BitMap::read_netpbm(image.as_slice(), &options).unwrap();
+            for i in 0..5 {
+                for j in 0..5 {
+                    assert_eq!(read.get(i, j), bit_map.get(i, j));
+                }
+            }
+        }
+
+        let image = GrayImage::read_netpbm(&b"P2\n# comment\n2 1\n15\n0 15\n"[..]).unwrap();
+        assert_eq!(image.pixels, vec![0, 255]);
+        assert!(matches!(
+            GrayImage::read_netpbm(&b"P6\n1 1\n255\n\0\0\0"[..]),
+            Err(NetpbmError::UnsupportedFormat)
+        ));
+
+        // Sizes beyond the data, including ones that overflow, are rejected before allocating
+        for header in [
+            &b"P5\n4294967296 4294967296\n255\n\0"[..],
+            &b"P5\n65536 65536\n255\n\0"[..],
+            &b"P4\n18446744073709551615 2\n\0"[..],
+            &b"P1\n3 3\n0 1 0\n"[..],
+        ] {
+            assert!(matches!(
+                GrayImage::read_netpbm(header),
+                Err(NetpbmError::InvalidData)
+            ));
+        }
+    }
+}
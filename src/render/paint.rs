@@ -0,0 +1,252 @@
+//! # Paint
+//!
+//! Colours and gradients for the dark modules of each region of the symbol. The finder
+//! patterns, alignment patterns, timing patterns and data modules can each have their own
+//! paint, regions without one use the dark colour. Gradients are laid out over the symbol
+//! without the quiet zone, so they run on across the regions that share them.
+//!
+//! ```rust,ignore
+//! let colors = RegionColors {
+//!     finder: Some(Paint::Solid(Color::rgb(0xE4, 0x00, 0x46))),
+//!     data: Some(Paint::Radial {
+//!         centre: Color::rgb(0x20, 0x20, 0x60),
+//!         edge: Color::BLACK,
+//!     }),
+//!     ..RegionColors::default()
+//! };
+//! let options = RenderOptions { colors, ..RenderOptions::default() };
+//!
+//! for warning in options.contrast_warnings() {
+//!     eprintln!("{}", warning);
+//! }
+//! ```
+
+use super::Color;
+use crate::bit_utils::bitmap::ModuleRole;
+use std::fmt::Display;
+
+/// Lowest contrast ratio between the light colour and the dark modules that scanners read
+/// reliably
+pub const MIN_CONTRAST_RATIO: f64 = 3.0;
+
+/// Fill of the dark modules of a region
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Paint {
+    Solid(Color),
+    /// Gradient along a line between two points, given as fractions of the symbol width from its
+    /// top left corner
+    Linear {
+        start: Color,
+        end: Color,
+        from: (f64, f64),
+        to: (f64, f64),
+    },
+    /// Gradient from the centre of the symbol to a circle touching its edges
+    Radial {
+        centre: Color,
+        edge: Color,
+    },
+}
+
+impl Paint {
+    /// Returns the colour of the paint at a point of the symbol
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Horizontal position as a fraction of the symbol width from the left
+    /// * `y` - Vertical position as a fraction of the symbol width from the top
+    pub fn color_at(&self, x: f64, y: f64) -> Color {
+        match *self {
+            Paint::Solid(color) => color,
+            Paint::Linear {
+                start,
+                end,
+                from,
+                to,
+            } => {
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                let length = dx * dx + dy * dy;
+
+                // Position of the point projected on the line, the ends are extended
+                let t = if length > 0.0 {
+                    ((x - from.0) * dx + (y - from.1) * dy) / length
+                } else {
+                    0.0
+                };
+
+                start.mix(end, t)
+            }
+            Paint::Radial { centre, edge } => {
+                let (dx, dy) = (x - 0.5, y - 0.5);
+                centre.mix(edge, (dx * dx + dy * dy).sqrt() / 0.5)
+            }
+        }
+    }
+
+    /// Returns the colours the paint is made of
+    pub fn colors(&self) -> Vec<Color> {
+        match *self {
+            Paint::Solid(color) => vec![color],
+            Paint::Linear { start, end, .. } => vec![start, end],
+            Paint::Radial { centre, edge } => vec![centre, edge],
+        }
+    }
+}
+
+/// Region of the symbol with its own paint
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Region {
+    /// Data modules and the function patterns without a region of their own
+    Data,
+    Finder,
+    Alignment,
+    Timing,
+}
+
+impl Region {
+    pub const ALL: [Region; 4] = [
+        Region::Data,
+        Region::Finder,
+        Region::Alignment,
+        Region::Timing,
+    ];
+
+    /// Returns the region a module belongs to
+    pub fn of(role: ModuleRole) -> Self {
+        match role {
+            ModuleRole::FinderOuter | ModuleRole::FinderInner => Region::Finder,
+            ModuleRole::Alignment => Region::Alignment,
+            ModuleRole::Timing => Region::Timing,
+            _ => Region::Data,
+        }
+    }
+
+    /// Lowercase name of the region
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::Data => "data",
+            Region::Finder => "finder",
+            Region::Alignment => "alignment",
+            Region::Timing => "timing",
+        }
+    }
+}
+
+/// Paints of the dark modules by region, `None` uses the dark colour
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct RegionColors {
+    pub data: Option<Paint>,
+    pub finder: Option<Paint>,
+    pub alignment: Option<Paint>,
+    pub timing: Option<Paint>,
+}
+
+impl RegionColors {
+    /// Returns the paint of a region
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - Region of the symbol
+    /// * `dark` - Colour of the regions without a paint
+    pub fn paint(&self, region: Region, dark: Color) -> Paint {
+        let paint = match region {
+            Region::Data => self.data,
+            Region::Finder => self.finder,
+            Region::Alignment => self.alignment,
+            Region::Timing => self.timing,
+        };
+
+        paint.unwrap_or(Paint::Solid(dark))
+    }
+
+    /// Checks every colour of every region against the light colour
+    ///
+    /// # Returns
+    ///
+    /// A warning for every colour with a contrast ratio below `MIN_CONTRAST_RATIO`
+    pub fn contrast_warnings(&self, dark: Color, light: Color) -> Vec<ContrastWarning> {
+        let mut warnings = Vec::new();
+
+        for region in Region::ALL {
+            for color in self.paint(region, dark).colors() {
+                let ratio = light.contrast_ratio(color);
+
+                if ratio < MIN_CONTRAST_RATIO
+                    && !warnings
+                        .iter()
+                        .any(|warning: &ContrastWarning| warning.color == color)
+                {
+                    warnings.push(ContrastWarning {
+                        region,
+                        color,
+                        ratio,
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+/// A dark colour too close to the light colour for scanners
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ContrastWarning {
+    /// First region using the colour
+    pub region: Region,
+    pub color: Color,
+    /// Luminance of the light colour relative to the dark one, below 1 when inverted
+    pub ratio: f64,
+}
+
+impl Display for ContrastWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} colour {} has a contrast ratio of {:.2}:1 with the light colour, scanners need at least {}:1",
+            self.region.name(),
+            self.color,
+            self.ratio,
+            MIN_CONTRAST_RATIO
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paints() {
+        let linear = Paint::Linear {
+            start: Color::BLACK,
+            end: Color::rgb(200, 0, 100),
+            from: (0.0, 0.0),
+            to: (1.0, 0.0),
+        };
+        assert_eq!(linear.color_at(-1.0, 0.3), Color::BLACK);
+        assert_eq!(linear.color_at(0.5, 0.9), Color::rgb(100, 0, 50));
+        assert_eq!(linear.color_at(2.0, 0.0), Color::rgb(200, 0, 100));
+
+        let radial = Paint::Radial {
+            centre: Color::WHITE,
+            edge: Color::BLACK,
+        };
+        assert_eq!(radial.color_at(0.5, 0.5), Color::WHITE);
+        assert_eq!(radial.color_at(0.5, 0.0), Color::BLACK);
+
+        let colors = RegionColors {
+            finder: Some(Paint::Solid(Color::rgb(255, 200, 0))),
+            ..RegionColors::default()
+        };
+        assert_eq!(
+            colors.paint(Region::of(ModuleRole::Separator), Color::BLACK),
+            Paint::Solid(Color::BLACK)
+        );
+
+        let warnings = colors.contrast_warnings(Color::BLACK, Color::WHITE);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].region, Region::Finder);
+        assert!(warnings[0].ratio < 2.0);
+    }
+}
@@ -0,0 +1,372 @@
+//! # PDF
+//!
+//! A dependency-free PDF writer. Every QR code is placed on its own page as vector rectangles,
+//! one per horizontal run of dark modules, with an optional caption set in Helvetica below the
+//! symbol. The content streams are compressed with the built-in deflate encoder.
+//!
+//! ```rust,ignore
+//! let mut document = PdfDocument::new();
+//! document.add_page(&first, &PdfOptions::default());
+//! document.add_page(&second, &PdfOptions::default());
+//! document.save("labels.pdf")?;
+//! ```
+
+use super::deflate::zlib_compress;
+use super::format_number;
+use crate::bit_utils::bitmap::BitMap;
+use crate::qr_code::Charset;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// Size of a PDF page
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PageSize {
+    /// 210 x 297 mm
+    A4,
+    /// 148 x 210 mm
+    A5,
+    /// 8.5 x 11 inches
+    Letter,
+    /// Width and height in mm
+    Custom { width: f64, height: f64 },
+}
+
+impl PageSize {
+    /// Returns the width and height of the page in mm
+    pub fn dimensions(&self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Custom { width, height } => (*width, *height),
+        }
+    }
+}
+
+/// Options for placing a QR code on a PDF page
+#[derive(PartialEq, Debug, Clone)]
+pub struct PdfOptions {
+    /// Size of the page
+    pub page_size: PageSize,
+    /// Distance in mm of the top left corner of the quiet zone from the left and top edges of the
+    /// page
+    pub position: (f64, f64),
+    /// Size of a module in mm
+    pub module_size: f64,
+    /// Width of the quiet zone around the symbol in modules
+    pub quiet_zone: usize,
+    /// Text set below the quiet zone
+    pub caption: Option<String>,
+    /// Font size of the caption in points
+    pub caption_size: f64,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            position: (20.0, 20.0),
+            module_size: 0.5,
+            quiet_zone: 4,
+            caption: None,
+            caption_size: 10.0,
+        }
+    }
+}
+
+/// Escapes text for a PDF string literal in WinAnsiEncoding
+///
+/// WinAnsiEncoding is Windows-1252, so characters such as € and the typographic quotes and
+/// dashes use the codes 0x80 - 0x9F. Characters it cannot represent become '?'
+fn pdf_string(text: &str) -> String {
+    let mut output = String::with_capacity(text.len() + 2);
+    output.push('(');
+
+    for character in text.chars() {
+        match character {
+            '(' | ')' | '\\' => {
+                output.push('\\');
+                output.push(character);
+            }
+            ' '..='~' => output.push(character),
+            _ if character.is_ascii() => output.push('?'),
+            _ => match Charset::Windows1252.encode(character.encode_utf8(&mut [0; 4])) {
+                Some(bytes) => output.push_str(&format!("\\{:03o}", bytes[0])),
+                None => output.push('?'),
+            },
+        }
+    }
+
+    output.push(')');
+    output
+}
+
+/// A PDF document with one QR code per page
+#[derive(Default)]
+pub struct PdfDocument {
+    /// Media box and content stream of every page
+    pages: Vec<((f64, f64), Vec<u8>)>,
+}
+
+impl PdfDocument {
+    /// Creates an empty document
+    pub fn new() -> Self {
+        Self { pages: Vec::new() }
+    }
+
+    /// Adds a page with the QR code placed on it
+    ///
+    /// # Arguments
+    ///
+    /// * `bit_map` - The QR code to place
+    /// * `options` - Page size, position, module size and caption
+    pub fn add_page(&mut self, bit_map: &BitMap, options: &PdfOptions) {
+        let (width, height) = options.page_size.dimensions();
+        let page = (width * POINTS_PER_MM, height * POINTS_PER_MM);
+
+        self.pages
+            .push((page, bit_map.pdf_content(page.1, options)));
+    }
+
+    /// Writes the document
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the PDF file
+    pub fn write<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        // Objects 1 - 3 are the catalog, the page tree and the caption font, followed by a page
+        // and its content stream for every page
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len())
+                    .map(|page| format!("{} 0 R", 4 + 2 * page))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                self.pages.len()
+            )
+            .into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        ];
+
+        for (index, ((width, height), content)) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                    format_number(*width),
+                    format_number(*height),
+                    5 + 2 * index
+                )
+                .into_bytes(),
+            );
+
+            let compressed = zlib_compress(content);
+            let mut stream = format!(
+                "<< /Length {} /Filter /FlateDecode >>\nstream\n",
+                compressed.len()
+            )
+            .into_bytes();
+            stream.extend_from_slice(&compressed);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        // The binary comment marks the file as containing binary data
+        let mut output: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets: Vec<usize> = Vec::with_capacity(objects.len());
+
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(output.len());
+            output.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            output.extend_from_slice(object);
+            output.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = output.len();
+        output.extend_from_slice(format!("xref\n0 {}\n", objects.len() + 1).as_bytes());
+        output.extend_from_slice(b"0000000000 65535 f \n");
+        for offset in offsets {
+            output.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+
+        output.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+
+        writer.write_all(&output)
+    }
+
+    /// Saves the document to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the document
+    pub fn save<P>(&self, path: P) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
+
+impl BitMap {
+    /// Creates the content stream drawing the QR code and its caption
+    fn pdf_content(&self, page_height: f64, options: &PdfOptions) -> Vec<u8> {
+        let modules = self.size() + 2 * options.quiet_zone;
+        let module_size = options.module_size * POINTS_PER_MM;
+
+        // Bottom left corner of the quiet zone, PDF coordinates start at the bottom of the page
+        let left = options.position.0 * POINTS_PER_MM;
+        let bottom =
+            page_height - options.position.1 * POINTS_PER_MM - modules as f64 * module_size;
+
+        // Draw in module units
+        let mut content = format!(
+            "q\n{0} 0 0 {0} {1} {2} cm\n0 g\n",
+            format_number(module_size),
+            format_number(left),
+            format_number(bottom)
+        );
+
+        for row in 0..self.size() {
+            let y = modules - options.quiet_zone - row - 1;
+
+            for (column, length) in self.dark_runs(row) {
+                content.push_str(&format!(
+                    "{} {} {} 1 re\n",
+                    column + options.quiet_zone,
+                    y,
+                    length
+                ));
+            }
+        }
+        content.push_str("f\nQ\n");
+
+        if let Some(caption) = &options.caption {
+            content.push_str(&format!(
+                "BT\n/F1 {} Tf\n{} {} Td\n{} Tj\nET\n",
+                format_number(options.caption_size),
+                format_number(left + options.quiet_zone as f64 * module_size),
+                format_number(bottom - options.caption_size),
+                pdf_string(caption)
+            ));
+        }
+
+        content.into_bytes()
+    }
+
+    /// Writes the bitmap as a single page PDF document
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the PDF file
+    /// * `options` - Page size, position, module size and caption
+    pub fn write_pdf<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &PdfOptions,
+    ) -> io::Result<()> {
+        let mut document = PdfDocument::new();
+        document.add_page(self, options);
+        document.write(writer)
+    }
+
+    /// Saves the bitmap to a file as a single page PDF document
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the document
+    /// * `options` - Page size, position, module size and caption
+    pub fn save_pdf<P>(&self, path: P, options: &PdfOptions) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_pdf(&mut writer, options)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_content() {
+        let mut bit_map = BitMap::new(2);
+        bit_map.set(0, 0, 1);
+        bit_map.set(0, 1, 1);
+        bit_map.set(1, 1, 1);
+
+        let options = PdfOptions {
+            page_size: PageSize::Custom {
+                width: 25.4,
+                height: 25.4,
+            },
+            position: (2.54, 2.54),
+            module_size: 2.54,
+            quiet_zone: 1,
+            caption: Some("Lot (42)".to_string()),
+            caption_size: 6.0,
+        };
+
+        // 72 x 72 pt page, 7.2 pt modules and the symbol 7.2 pt from the top left corner
+        let content = String::from_utf8(bit_map.pdf_content(72.0, &options)).unwrap();
+        assert_eq!(
+            content,
+            "q\n7.2 0 0 7.2 7.2 36 cm\n0 g\n1 2 2 1 re\n2 1 1 1 re\nf\nQ\n\
+             BT\n/F1 6 Tf\n14.4 30 Td\n(Lot \\(42\\)) Tj\nET\n"
+        );
+
+        // Latin-1 characters keep their code, € and the typographic characters use 0x80 - 0x9F
+        assert_eq!(
+            pdf_string("é € – “Œ” 中"),
+            "(\\351 \\200 \\226 \\223\\214\\224 ?)"
+        );
+    }
+
+    #[test]
+    fn test_pdf_document() {
+        let bit_map = BitMap::new(21);
+
+        let mut document = PdfDocument::new();
+        document.add_page(&bit_map, &PdfOptions::default());
+        document.add_page(&bit_map, &PdfOptions::default());
+
+        let mut pdf: Vec<u8> = Vec::new();
+        document.write(&mut pdf).unwrap();
+
+        // Everything but the compressed streams is ASCII, so offsets are checked on the bytes
+        let starts_with =
+            |offset: usize, prefix: &str| pdf[offset..].starts_with(prefix.as_bytes());
+        let trailer = String::from_utf8_lossy(&pdf[pdf.len() - 64..]).into_owned();
+
+        assert!(starts_with(0, "%PDF-1.4\n"));
+        assert!(trailer.ends_with("%%EOF\n"));
+        let kids = b"/Kids [4 0 R 6 0 R] /Count 2";
+        assert!(pdf.windows(kids.len()).any(|window| window == kids));
+
+        // The cross-reference table points at every object
+        let xref: usize = trailer.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(starts_with(xref, "xref\n0 8\n0000000000 65535 f \n"));
+
+        for object in 1..8 {
+            let entry = xref + 9 + 20 * object;
+            let offset: usize = String::from_utf8_lossy(&pdf[entry..entry + 10])
+                .parse()
+                .unwrap();
+            assert!(starts_with(offset, &format!("{} 0 obj", object)));
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! # PNG
+//!
+//! A self-contained PNG writer. The smallest pixel format for the colours is picked: 1-bit
+//! grayscale for black on white, 8-bit grayscale for other grays, 8-bit RGB for region colours
+//! and gradients and a 1-bit palette otherwise. The pixel data is compressed with the built-in
+//! deflate encoder.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! qr_code.save_png("qr_code.png", &RenderOptions::default())?;
+//! ```
+
+use super::deflate::zlib_compress;
+use super::paint::RegionColors;
+use super::sizing::pixels_per_metre;
+use super::{Color, RenderOptions};
+use crate::bit_utils::bitmap::BitMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Pixel format of the PNG image
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum PngColorType {
+    /// Black and white, one bit per pixel
+    Grayscale1,
+    /// Two gray levels, one byte per pixel
+    Grayscale8,
+    /// One bit per pixel indexing a two colour palette
+    Palette,
+    /// Red, green and blue bytes, for region colours and gradients
+    Rgb,
+}
+
+impl PngColorType {
+    fn for_options(options: &RenderOptions) -> Self {
+        let (dark, light) = (options.dark, options.light);
+        let gray = |color: Color| color.r == color.g && color.g == color.b;
+
+        if options.colors != RegionColors::default() {
+            PngColorType::Rgb
+        } else if dark == Color::BLACK && light == Color::WHITE {
+            PngColorType::Grayscale1
+        } else if gray(dark) && gray(light) {
+            PngColorType::Grayscale8
+        } else {
+            PngColorType::Palette
+        }
+    }
+}
+
+/// Computes the CRC-32 checksum used by PNG chunks
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+fn write_chunk<W: Write + ?Sized>(
+    writer: &mut W,
+    chunk_type: &[u8; 4],
+    data: &[u8],
+) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+
+    let mut chunk = Vec::with_capacity(data.len() + 4);
+    chunk.extend_from_slice(chunk_type);
+    chunk.extend_from_slice(data);
+
+    writer.write_all(&chunk)?;
+    writer.write_all(&crc32(&chunk).to_be_bytes())
+}
+
+/// Writes the resolution of the image as a pHYs chunk, in pixels per metre
+fn write_resolution<W: Write + ?Sized>(writer: &mut W, dpi: Option<u32>) -> io::Result<()> {
+    let Some(dpi) = dpi else {
+        return Ok(());
+    };
+
+    let resolution = pixels_per_metre(dpi).to_be_bytes();
+
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.extend_from_slice(&resolution);
+    data.extend_from_slice(&resolution);
+    data.push(1);
+
+    write_chunk(writer, b"pHYs", &data)
+}
+
+/// Writes 8-bit RGBA pixels as a PNG image
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the image data
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `pixels` - Rows of pixels from the top left, four bytes per pixel
+/// * `dpi` - Resolution to store in the image, if any
+pub(crate) fn write_rgba_png<W: Write + ?Sized>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+    dpi: Option<u32>,
+) -> io::Result<()> {
+    writer.write_all(&SIGNATURE)?;
+
+    let mut header: Vec<u8> = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(writer, b"IHDR", &header)?;
+    write_resolution(writer, dpi)?;
+
+    let mut scanlines: Vec<u8> = Vec::with_capacity((width * 4 + 1) * height);
+    for row in pixels.chunks(width * 4).take(height) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    write_chunk(writer, b"IDAT", &zlib_compress(&scanlines))?;
+    write_chunk(writer, b"IEND", &[])
+}
+
+impl BitMap {
+    /// Creates the filtered scanlines of the image, every row starts with filter type 0 (none)
+    fn png_scanlines(&self, options: &RenderOptions) -> Vec<u8> {
+        let width = options.image_size(self);
+        let color_type = PngColorType::for_options(options);
+
+        let row_bytes = match color_type {
+            PngColorType::Grayscale8 => width,
+            PngColorType::Rgb => width * 3,
+            _ => width.div_ceil(8),
+        };
+
+        let mut scanlines: Vec<u8> = Vec::with_capacity((row_bytes + 1) * width);
+
+        for y in 0..width {
+            let mut scanline = vec![0u8; row_bytes];
+
+            for x in 0..width {
+                let dark = self.is_dark_pixel(x, y, options);
+
+                match color_type {
+                    // Black is 0 in grayscale images
+                    PngColorType::Grayscale1 if !dark => scanline[x / 8] |= 0x80 >> (x % 8),
+                    PngColorType::Grayscale8 => {
+                        scanline[x] = if dark {
+                            options.dark.r
+                        } else {
+                            options.light.r
+                        }
+                    }
+                    // The dark colour is the second palette entry
+                    PngColorType::Palette if dark => scanline[x / 8] |= 0x80 >> (x % 8),
+                    PngColorType::Rgb => {
+                        let color = self.pixel_color(x, y, options);
+                        scanline[3 * x..3 * x + 3].copy_from_slice(&[color.r, color.g, color.b]);
+                    }
+                    _ => {}
+                }
+            }
+
+            scanlines.push(0);
+            scanlines.extend_from_slice(&scanline);
+        }
+
+        scanlines
+    }
+
+    /// Writes the bitmap as a PNG image
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn write_png<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        self.write_png_with_dpi(writer, options, None)
+    }
+
+    /// Writes the bitmap as a PNG image that stores its resolution
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Module size, quiet zone and colours of the image
+    /// * `dpi` - Resolution to store in the image, if any
+    pub(crate) fn write_png_with_dpi<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+        dpi: Option<u32>,
+    ) -> io::Result<()> {
+        let width = options.image_size(self) as u32;
+        let color_type = PngColorType::for_options(options);
+
+        let (bit_depth, color_type_code) = match color_type {
+            PngColorType::Grayscale1 => (1, 0),
+            PngColorType::Grayscale8 => (8, 0),
+            PngColorType::Palette => (1, 3),
+            PngColorType::Rgb => (8, 2),
+        };
+
+        writer.write_all(&SIGNATURE)?;
+
+        // Width, height, bit depth, colour type, compression, filter and interlace method
+        let mut header: Vec<u8> = Vec::with_capacity(13);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&[bit_depth, color_type_code, 0, 0, 0]);
+        write_chunk(writer, b"IHDR", &header)?;
+
+        if color_type == PngColorType::Palette {
+            let (dark, light) = (options.dark, options.light);
+            write_chunk(
+                writer,
+                b"PLTE",
+                &[light.r, light.g, light.b, dark.r, dark.g, dark.b],
+            )?;
+        }
+
+        write_resolution(writer, dpi)?;
+
+        let image_data = zlib_compress(&self.png_scanlines(options));
+        write_chunk(writer, b"IDAT", &image_data)?;
+        write_chunk(writer, b"IEND", &[])
+    }
+
+    /// Saves the bitmap to a file in PNG format
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn save_png<P>(&self, path: P, options: &RenderOptions) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png(&mut writer, options)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_png() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, 1);
+
+        let mut options = RenderOptions {
+            module_px: 2,
+            quiet_zone_modules: 1,
+            ..RenderOptions::default()
+        };
+
+        // 6 x 6 pixels with the dark module in the middle
+        assert_eq!(
+            bit_map.png_scanlines(&options),
+            vec![0, 0xFC, 0, 0xFC, 0, 0xCC, 0, 0xCC, 0, 0xFC, 0, 0xFC]
+        );
+
+        let mut png: Vec<u8> = Vec::new();
+        bit_map.write_png(&mut png, &options).unwrap();
+
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..26], [0, 0, 0, 6, 0, 0, 0, 6, 1, 0]);
+        assert_eq!(&png[png.len() - 8..], b"IEND\xAE\x42\x60\x82");
+
+        // Other colours are written as a palette
+        options.dark = Color::rgb(0, 0, 128);
+
+        let mut png: Vec<u8> = Vec::new();
+        bit_map.write_png(&mut png, &options).unwrap();
+
+        assert_eq!(png[24..26], [1, 3]);
+        assert_eq!(&png[37..47], b"PLTE\xFF\xFF\xFF\x00\x00\x80");
+    }
+}
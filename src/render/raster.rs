@@ -0,0 +1,139 @@
+//! # Raster
+//!
+//! Raw pixel buffers for GUI toolkits and image libraries. The buffers are rows of 8-bit
+//! grayscale or RGBA pixels from the top left, which egui, GTK and the `image` crate take
+//! directly. The scanline iterator produces the same pixels one row at a time.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! let (width, height, pixels) = qr_code.to_rgba8(&RenderOptions::default());
+//!
+//! for row in qr_code.scanlines(&RenderOptions::default()) {
+//!     // row is a Vec<Color> of the pixels of one image row
+//! }
+//! ```
+
+use super::{Color, RenderOptions};
+use crate::bit_utils::bitmap::BitMap;
+
+/// Iterator over the pixel rows of a bitmap image, from the top
+pub struct Scanlines<'a> {
+    bit_map: &'a BitMap,
+    options: RenderOptions,
+    width: usize,
+    y: usize,
+}
+
+impl Iterator for Scanlines<'_> {
+    type Item = Vec<Color>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.y >= self.width {
+            return None;
+        }
+
+        let row = (0..self.width)
+            .map(|x| self.bit_map.pixel_color(x, self.y, &self.options))
+            .collect();
+        self.y += 1;
+
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.width - self.y;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Scanlines<'_> {}
+
+impl BitMap {
+    /// Returns an iterator over the pixel rows of the image of the bitmap
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn scanlines(&self, options: &RenderOptions) -> Scanlines<'_> {
+        Scanlines {
+            bit_map: self,
+            options: *options,
+            width: options.image_size(self),
+            y: 0,
+        }
+    }
+
+    /// Renders the bitmap as an 8-bit grayscale pixel buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the image, as their luma
+    ///
+    /// # Returns
+    ///
+    /// The width, height and pixels of the image, one byte per pixel
+    pub fn to_luma8(&self, options: &RenderOptions) -> (usize, usize, Vec<u8>) {
+        let width = options.image_size(self);
+        let pixels = self
+            .scanlines(options)
+            .flat_map(|row| row.into_iter().map(|color| color.luma()))
+            .collect();
+
+        (width, width, pixels)
+    }
+
+    /// Renders the bitmap as an 8-bit RGBA pixel buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the image
+    ///
+    /// # Returns
+    ///
+    /// The width, height and pixels of the image, four bytes per pixel with opaque alpha
+    pub fn to_rgba8(&self, options: &RenderOptions) -> (usize, usize, Vec<u8>) {
+        let width = options.image_size(self);
+        let pixels = self
+            .scanlines(options)
+            .flat_map(|row| {
+                row.into_iter()
+                    .flat_map(|color| [color.r, color.g, color.b, 255])
+            })
+            .collect();
+
+        (width, width, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_buffers() {
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, 1);
+
+        let options = RenderOptions {
+            module_px: 2,
+            quiet_zone_modules: 1,
+            dark: Color::rgb(0, 0, 255),
+            ..RenderOptions::default()
+        };
+
+        let scanlines = bit_map.scanlines(&options);
+        assert_eq!(scanlines.len(), 6);
+
+        let rows: Vec<Vec<Color>> = scanlines.collect();
+        assert_eq!(rows[2][1], Color::WHITE);
+        assert_eq!(rows[2][2], Color::rgb(0, 0, 255));
+
+        let (width, height, luma) = bit_map.to_luma8(&options);
+        assert_eq!((width, height, luma.len()), (6, 6, 36));
+        assert_eq!(luma[2 * 6 + 1..2 * 6 + 5], [255, 29, 29, 255]);
+
+        let (_, _, rgba) = bit_map.to_rgba8(&options);
+        assert_eq!(rgba.len(), 36 * 4);
+        assert_eq!(rgba[(3 * 6 + 3) * 4..(3 * 6 + 4) * 4], [0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,155 @@
+//! # Sixel
+//!
+//! Encodes QR codes as Sixel graphics, which terminals such as xterm, mlterm, foot and WezTerm
+//! display as images. Each band of six pixel rows is drawn once per colour, with repeated
+//! columns run-length encoded.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! print!("{}", qr_code.to_sixel(&RenderOptions::default()));
+//! ```
+
+use super::RenderOptions;
+use crate::bit_utils::bitmap::BitMap;
+use std::io::{self, Write};
+
+/// Converts a colour component to the 0 - 100 range of Sixel colour registers
+fn percent(component: u8) -> u32 {
+    (component as u32 * 100 + 127) / 255
+}
+
+/// Appends a sixel, repeated runs of more than 3 are written as `!count sixel`
+fn push_run(output: &mut String, sixel: char, count: usize) {
+    if count > 3 {
+        output.push_str(&format!("!{}{}", count, sixel));
+    } else {
+        output.extend(std::iter::repeat_n(sixel, count));
+    }
+}
+
+impl BitMap {
+    /// Encodes the bitmap as a Sixel image
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the image
+    ///
+    /// # Returns
+    ///
+    /// The device control string, from the introducer to the string terminator
+    pub fn to_sixel(&self, options: &RenderOptions) -> String {
+        let width = options.image_size(self);
+
+        // Pixel aspect ratio 1:1, unset pixels stay transparent
+        let mut output = format!("\x1bP0;1;0q\"1;1;{};{}", width, width);
+
+        for (register, color) in [options.light, options.dark].iter().enumerate() {
+            output.push_str(&format!(
+                "#{};2;{};{};{}",
+                register,
+                percent(color.r),
+                percent(color.g),
+                percent(color.b)
+            ));
+        }
+
+        for band in (0..width).step_by(6) {
+            if band > 0 {
+                output.push('-');
+            }
+
+            let mut first_color = true;
+
+            for (register, dark) in [(0, false), (1, true)] {
+                let sixels: Vec<char> = (0..width)
+                    .map(|x| {
+                        let bits = (0..6)
+                            .filter(|dy| band + dy < width)
+                            .filter(|dy| self.is_dark_pixel(x, band + dy, options) == dark)
+                            .fold(0u8, |bits, dy| bits | (1 << dy));
+
+                        (63 + bits) as char
+                    })
+                    .collect();
+
+                // Trailing empty sixels do not need to be drawn
+                let len = sixels.iter().rposition(|sixel| *sixel != '?');
+                let Some(len) = len.map(|last| last + 1) else {
+                    continue;
+                };
+
+                if !first_color {
+                    output.push('$');
+                }
+                first_color = false;
+
+                output.push_str(&format!("#{}", register));
+
+                let mut index = 0;
+                while index < len {
+                    let sixel = sixels[index];
+                    let count = sixels[index..len]
+                        .iter()
+                        .take_while(|other| **other == sixel)
+                        .count();
+
+                    push_run(&mut output, sixel, count);
+                    index += count;
+                }
+            }
+        }
+
+        output.push_str("\x1b\\");
+        output
+    }
+
+    /// Writes the bitmap as a Sixel image
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the device control string
+    /// * `options` - Module size, quiet zone and colours of the image
+    pub fn write_sixel<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        writer.write_all(self.to_sixel(options).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Color;
+
+    #[test]
+    fn test_to_sixel() {
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, 1);
+
+        let options = RenderOptions {
+            module_px: 1,
+            quiet_zone_modules: 1,
+            dark: Color::BLACK,
+            light: Color::rgb(255, 0, 0),
+            ..RenderOptions::default()
+        };
+
+        assert_eq!(
+            bit_map.to_sixel(&options),
+            "\x1bP0;1;0q\"1;1;3;3#0;2;100;0;0#1;2;0;0;0#0FDF$#1?A\x1b\\"
+        );
+
+        // 9 x 9 pixels: a full band of six rows and a band of three rows
+        let options = RenderOptions {
+            module_px: 3,
+            ..options
+        };
+
+        assert_eq!(
+            bit_map.to_sixel(&options),
+            "\x1bP0;1;0q\"1;1;9;9#0;2;100;0;0#1;2;0;0;0#0~~~FFF~~~$#1???www-#0!9F\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! # Sizing
+//!
+//! Renders QR codes at a physical size. Label layouts give the module size in millimetres,
+//! inches or points together with the resolution of the printer, which rarely works out to a
+//! whole number of pixels per module. The module size is either snapped to the nearest whole
+//! number of pixels, which keeps the edges sharp but changes the printed size slightly, or kept
+//! exact with anti-aliased edges, which scanners read less reliably. The resolution is stored in
+//! the image, as a pHYs chunk in PNG and in pixels per metre in BMP.
+//!
+//! ```rust,ignore
+//! let size = PhysicalSize {
+//!     module_size: Length::Millimetres(0.5),
+//!     dpi: 300,
+//!     rounding: Rounding::Snap,
+//! };
+//! qr_code.save_png_sized("label.png", &size, &RenderOptions::default())?;
+//! ```
+
+use super::bmp::{write_bmp_image, BmpFormat, BmpOptions};
+use super::png::write_rgba_png;
+use super::{Color, RenderOptions};
+use crate::bit_utils::bitmap::BitMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const MILLIMETRES_PER_INCH: f64 = 25.4;
+const POINTS_PER_INCH: f64 = 72.0;
+
+/// Samples per pixel in each direction when anti-aliasing
+const SUPERSAMPLING: usize = 4;
+
+/// Pixels per module of the image the anti-aliased pixels are sampled from
+const SAMPLE_MODULE_PX: usize = 16;
+
+/// Converts a resolution in dots per inch to pixels per metre
+pub(crate) fn pixels_per_metre(dpi: u32) -> u32 {
+    ((dpi as u64 * 10_000 + 127) / 254) as u32
+}
+
+/// A physical length
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Length {
+    Millimetres(f64),
+    Inches(f64),
+    /// PostScript points, 1/72 inch
+    Points(f64),
+}
+
+impl Length {
+    /// Returns the length in inches
+    pub fn inches(&self) -> f64 {
+        match *self {
+            Length::Millimetres(millimetres) => millimetres / MILLIMETRES_PER_INCH,
+            Length::Inches(inches) => inches,
+            Length::Points(points) => points / POINTS_PER_INCH,
+        }
+    }
+
+    /// Returns the length in millimetres
+    pub fn millimetres(&self) -> f64 {
+        self.inches() * MILLIMETRES_PER_INCH
+    }
+}
+
+/// Handling of module sizes that are not a whole number of pixels
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Rounding {
+    /// Round to the nearest whole number of pixels, at least 1, keeping the edges sharp
+    Snap,
+    /// Keep the exact size and blend the pixels on the module edges
+    AntiAlias,
+}
+
+/// Physical module size and resolution of a raster image
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct PhysicalSize {
+    /// Width of a module when printed
+    pub module_size: Length,
+    /// Resolution of the printer in dots per inch
+    pub dpi: u32,
+    pub rounding: Rounding,
+}
+
+impl Default for PhysicalSize {
+    /// 0.5 mm modules at 300 DPI, snapped to whole pixels
+    fn default() -> Self {
+        Self {
+            module_size: Length::Millimetres(0.5),
+            dpi: 300,
+            rounding: Rounding::Snap,
+        }
+    }
+}
+
+impl PhysicalSize {
+    /// Returns the exact size of a module in pixels
+    pub fn exact_module_px(&self) -> f64 {
+        self.module_size.inches() * self.dpi as f64
+    }
+
+    /// Returns the size of a module snapped to the nearest whole number of pixels, at least 1
+    pub fn module_px(&self) -> usize {
+        (self.exact_module_px().round() as usize).max(1)
+    }
+
+    /// Returns the width of a module as printed, after snapping
+    pub fn printed_module_size(&self) -> Length {
+        match self.rounding {
+            Rounding::Snap => Length::Inches(self.module_px() as f64 / self.dpi as f64),
+            Rounding::AntiAlias => self.module_size,
+        }
+    }
+
+    /// Returns the options with the module size snapped to whole pixels
+    pub fn render_options(&self, options: &RenderOptions) -> RenderOptions {
+        RenderOptions {
+            module_px: self.module_px(),
+            ..*options
+        }
+    }
+
+    /// Checks whether the modules are drawn with blended edges
+    ///
+    /// # Returns
+    ///
+    /// A warning when anti-aliasing a module size that is not a whole number of pixels
+    pub fn warning(&self) -> Option<SizingWarning> {
+        let module_px = self.exact_module_px();
+
+        if self.rounding == Rounding::AntiAlias && (module_px - module_px.round()).abs() > 1e-6 {
+            Some(SizingWarning { module_px })
+        } else {
+            None
+        }
+    }
+}
+
+/// A module size drawn with anti-aliased edges
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SizingWarning {
+    /// Exact size of a module in pixels
+    pub module_px: f64,
+}
+
+impl Display for SizingWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "module size of {:.2} px is not a whole number of pixels, its edges are anti-aliased and may not scan reliably",
+            self.module_px
+        )
+    }
+}
+
+impl BitMap {
+    /// Renders the bitmap at a physical size as an 8-bit RGBA pixel buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Module size, resolution and rounding of the image
+    /// * `options` - Quiet zone and colours of the image, the module size is replaced
+    ///
+    /// # Returns
+    ///
+    /// The width, height and pixels of the image, four bytes per pixel with opaque alpha
+    pub fn to_rgba8_sized(
+        &self,
+        size: &PhysicalSize,
+        options: &RenderOptions,
+    ) -> (usize, usize, Vec<u8>) {
+        if size.rounding == Rounding::Snap || size.warning().is_none() {
+            return self.to_rgba8(&size.render_options(options));
+        }
+
+        let module_px = size.exact_module_px();
+        let modules = self.size() + 2 * options.quiet_zone_modules;
+        let width = ((modules as f64 * module_px).round() as usize).max(1);
+
+        // Every pixel averages a grid of samples from a finer image
+        let sample_options = RenderOptions {
+            module_px: SAMPLE_MODULE_PX,
+            ..*options
+        };
+        let sample_width = sample_options.image_size(self);
+        let sample = |pixel: usize, offset: usize| {
+            let position = pixel as f64 + (offset as f64 + 0.5) / SUPERSAMPLING as f64;
+            ((position / module_px * SAMPLE_MODULE_PX as f64) as usize).min(sample_width - 1)
+        };
+
+        let mut pixels = Vec::with_capacity(width * width * 4);
+
+        for y in 0..width {
+            for x in 0..width {
+                let mut sum = [0u32; 3];
+
+                for i in 0..SUPERSAMPLING {
+                    for j in 0..SUPERSAMPLING {
+                        let color = self.pixel_color(sample(x, j), sample(y, i), &sample_options);
+
+                        sum[0] += color.r as u32;
+                        sum[1] += color.g as u32;
+                        sum[2] += color.b as u32;
+                    }
+                }
+
+                let count = (SUPERSAMPLING * SUPERSAMPLING) as u32;
+                pixels.extend(sum.map(|channel| ((channel + count / 2) / count) as u8));
+                pixels.push(255);
+            }
+        }
+
+        (width, width, pixels)
+    }
+
+    /// Writes the bitmap as a PNG image at a physical size, with its resolution
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `size` - Module size, resolution and rounding of the image
+    /// * `options` - Quiet zone and colours of the image, the module size is replaced
+    pub fn write_png_sized<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        size: &PhysicalSize,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        if size.warning().is_none() {
+            return self.write_png_with_dpi(writer, &size.render_options(options), Some(size.dpi));
+        }
+
+        let (width, height, pixels) = self.to_rgba8_sized(size, options);
+        write_rgba_png(writer, width, height, &pixels, Some(size.dpi))
+    }
+
+    /// Saves the bitmap to a file in PNG format at a physical size
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `size` - Module size, resolution and rounding of the image
+    /// * `options` - Quiet zone and colours of the image, the module size is replaced
+    pub fn save_png_sized<P>(
+        &self,
+        path: P,
+        size: &PhysicalSize,
+        options: &RenderOptions,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png_sized(&mut writer, size, options)?;
+        writer.flush()
+    }
+
+    /// Writes the bitmap as a BMP image at a physical size, with its resolution
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `format` - Pixel format of the image, anti-aliased images use `Rgb24` in place of the
+    ///   indexed formats
+    /// * `size` - Module size, resolution and rounding of the image
+    /// * `options` - Quiet zone and colours of the image, the module size is replaced
+    pub fn write_bmp_sized<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        format: BmpFormat,
+        size: &PhysicalSize,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let bmp_options = BmpOptions {
+            format,
+            dpi: size.dpi,
+        };
+
+        if size.warning().is_none() {
+            return self.write_bmp(writer, &bmp_options, &size.render_options(options));
+        }
+
+        let bmp_options = BmpOptions {
+            format: match format {
+                BmpFormat::Indexed1 | BmpFormat::Indexed8 => BmpFormat::Rgb24,
+                _ => format,
+            },
+            ..bmp_options
+        };
+
+        let (width, height, pixels) = self.to_rgba8_sized(size, options);
+        let threshold = (options.dark.luma() as u32 + options.light.luma() as u32) / 2;
+
+        write_bmp_image(
+            writer,
+            width,
+            height,
+            &bmp_options,
+            [options.light, options.dark],
+            |x, y| {
+                let offset = 4 * (y * width + x);
+                let color = Color::rgb(pixels[offset], pixels[offset + 1], pixels[offset + 2]);
+
+                ((color.luma() as u32) < threshold, color)
+            },
+        )
+    }
+
+    /// Saves the bitmap to a file in BMP format at a physical size
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `format` - Pixel format of the image
+    /// * `size` - Module size, resolution and rounding of the image
+    /// * `options` - Quiet zone and colours of the image, the module size is replaced
+    pub fn save_bmp_sized<P>(
+        &self,
+        path: P,
+        format: BmpFormat,
+        size: &PhysicalSize,
+        options: &RenderOptions,
+    ) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_bmp_sized(&mut writer, format, size, options)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_physical_size() {
+        assert_eq!(pixels_per_metre(300), 11811);
+
+        // Half a millimetre at 300 DPI is 5.9 pixels
+        let size = PhysicalSize::default();
+        assert!((size.exact_module_px() - 5.905).abs() < 0.001);
+        assert_eq!(size.module_px(), 6);
+        assert!((size.printed_module_size().millimetres() - 0.508).abs() < 0.001);
+        assert_eq!(size.warning(), None);
+
+        // 1.44 points at 300 DPI is exactly 6 pixels, nothing to blend
+        let exact = PhysicalSize {
+            module_size: Length::Points(1.44),
+            rounding: Rounding::AntiAlias,
+            ..size
+        };
+        assert_eq!(exact.warning(), None);
+
+        let anti_aliased = PhysicalSize {
+            rounding: Rounding::AntiAlias,
+            ..size
+        };
+        assert!(anti_aliased.warning().is_some());
+
+        let mut bit_map = BitMap::new(1);
+        bit_map.set(0, 0, 1);
+
+        let options = RenderOptions {
+            quiet_zone_modules: 1,
+            ..RenderOptions::default()
+        };
+
+        // 3 modules of 5.9 pixels, the module ends 0.8 pixels into pixel 11
+        let (width, _, pixels) = bit_map.to_rgba8_sized(&anti_aliased, &options);
+        assert_eq!(width, 18);
+        assert_eq!(pixels[4 * (8 * width + 8)], 0);
+        assert_eq!(pixels[4 * (8 * width + 11)], 64);
+        assert_eq!(pixels[4 * (8 * width + 12)], 255);
+
+        let mut png: Vec<u8> = Vec::new();
+        bit_map.write_png_sized(&mut png, &size, &options).unwrap();
+        assert_eq!(png[16..20], 18u32.to_be_bytes());
+        assert_eq!(&png[37..41], b"pHYs");
+        assert_eq!(png[41..50], [0, 0, 0x2E, 0x23, 0, 0, 0x2E, 0x23, 1]);
+    }
+}
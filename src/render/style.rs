@@ -0,0 +1,323 @@
+//! # Style
+//!
+//! Module shapes and finder pattern "eyes" for branded QR codes. Only data modules take the
+//! module shape and fill ratio, the finder patterns are drawn with the eye shapes and every
+//! other function pattern stays square, so scanners still find the symbol. The roles come from
+//! `create_bit_map`, bitmaps without roles are styled as data everywhere.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! let style = ModuleStyle {
+//!     shape: ModuleShape::Circle,
+//!     fill_ratio: 0.9,
+//!     outer_eye: EyeShape::Rounded,
+//!     inner_eye: EyeShape::Circle,
+//! };
+//!
+//! qr_code.save_png("qr_code.png", &RenderOptions { style, ..RenderOptions::default() })?;
+//! ```
+
+use super::format_number;
+use crate::bit_utils::bitmap::{BitMap, ModuleRole};
+
+/// Shape of the data modules
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ModuleShape {
+    Square,
+    Circle,
+    /// Square with corners rounded by half its size
+    RoundedSquare,
+    /// Square joined to its dark neighbours, with the corners rounded where it has none
+    Connected,
+}
+
+/// Shape of the rings and centres of the finder patterns
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum EyeShape {
+    Square,
+    /// Square with corners rounded by a quarter of its size
+    Rounded,
+    Circle,
+}
+
+impl EyeShape {
+    /// Corner radius of a square with the half size `half`, in modules
+    fn corner_radius(&self, half: f64) -> f64 {
+        match self {
+            EyeShape::Square => 0.0,
+            EyeShape::Rounded => half / 2.0,
+            EyeShape::Circle => half,
+        }
+    }
+}
+
+/// Styling of the modules and finder patterns
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ModuleStyle {
+    /// Shape of the data modules
+    pub shape: ModuleShape,
+    /// Size of the data modules relative to the module grid, between 0 and 1
+    pub fill_ratio: f64,
+    /// Shape of the outer ring of the finder patterns
+    pub outer_eye: EyeShape,
+    /// Shape of the 3 x 3 centre of the finder patterns
+    pub inner_eye: EyeShape,
+}
+
+impl Default for ModuleStyle {
+    /// Plain square modules filling the whole grid
+    fn default() -> Self {
+        Self {
+            shape: ModuleShape::Square,
+            fill_ratio: 1.0,
+            outer_eye: EyeShape::Square,
+            inner_eye: EyeShape::Square,
+        }
+    }
+}
+
+/// A rectangle with rounded corners, in modules
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub(crate) struct RoundedRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Corner radii from the top left, clockwise
+    pub radii: [f64; 4],
+}
+
+impl RoundedRect {
+    /// Creates a square centred on a point
+    fn centred(x: f64, y: f64, half: f64, radius: f64) -> Self {
+        Self {
+            x: x - half,
+            y: y - half,
+            width: 2.0 * half,
+            height: 2.0 * half,
+            radii: [radius; 4],
+        }
+    }
+
+    /// Checks whether a point is inside the rectangle
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let (right, bottom) = (self.x + self.width, self.y + self.height);
+
+        if x < self.x || x > right || y < self.y || y > bottom {
+            return false;
+        }
+
+        // Corner radii and directions, clockwise from the top left
+        let directions = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+        self.radii.iter().zip(directions).all(|(radius, (sx, sy))| {
+            let cx = if sx < 0.0 {
+                self.x + radius
+            } else {
+                right - radius
+            };
+            let cy = if sy < 0.0 {
+                self.y + radius
+            } else {
+                bottom - radius
+            };
+            let (dx, dy) = (x - cx, y - cy);
+
+            // Only points beyond the centre of the arc in both directions are in the corner
+            dx * sx <= 0.0 || dy * sy <= 0.0 || dx * dx + dy * dy <= radius * radius
+        })
+    }
+
+    /// Builds the SVG path data of the rectangle, drawn clockwise
+    pub fn svg_path(&self) -> String {
+        let [top_left, top_right, bottom_right, bottom_left] = self.radii;
+        let n = format_number;
+        let arc = |radius: f64, dx: f64, dy: f64| {
+            if radius > 0.0 {
+                format!("a{},{} 0 0 1 {},{}", n(radius), n(radius), n(dx), n(dy))
+            } else {
+                String::new()
+            }
+        };
+
+        format!(
+            "M{},{}h{}{}v{}{}h{}{}v{}{}z",
+            n(self.x + top_left),
+            n(self.y),
+            n(self.width - top_left - top_right),
+            arc(top_right, top_right, top_right),
+            n(self.height - top_right - bottom_right),
+            arc(bottom_right, -bottom_right, bottom_right),
+            n(-(self.width - bottom_right - bottom_left)),
+            arc(bottom_left, -bottom_left, -bottom_left),
+            n(-(self.height - bottom_left - top_left)),
+            arc(top_left, top_left, -top_left),
+        )
+    }
+}
+
+impl ModuleStyle {
+    /// Builds the shape of a dark data module
+    ///
+    /// # Arguments
+    ///
+    /// * `bit_map` - The QR code, for the neighbours of connected modules
+    /// * `row` - Row index of the module
+    /// * `column` - Column index of the module
+    ///
+    /// # Returns
+    ///
+    /// The shape in modules from the top left of the symbol
+    pub(crate) fn data_module(&self, bit_map: &BitMap, row: usize, column: usize) -> RoundedRect {
+        let half = self.fill_ratio.clamp(0.0, 1.0) / 2.0;
+        let (x, y) = (column as f64 + 0.5, row as f64 + 0.5);
+
+        match self.shape {
+            ModuleShape::Square => RoundedRect::centred(x, y, half, 0.0),
+            ModuleShape::Circle => RoundedRect::centred(x, y, half, half),
+            ModuleShape::RoundedSquare => RoundedRect::centred(x, y, half, half / 2.0),
+            ModuleShape::Connected => {
+                let dark = |row: Option<usize>, column: Option<usize>| {
+                    row.zip(column).is_some_and(|(row, column)| {
+                        bit_map.role(row, column) == ModuleRole::Data
+                            && bit_map.is_dark(row, column, 0)
+                    })
+                };
+
+                let top = dark(row.checked_sub(1), Some(column));
+                let right = dark(Some(row), Some(column + 1));
+                let bottom = dark(Some(row + 1), Some(column));
+                let left = dark(Some(row), column.checked_sub(1));
+
+                // Reach the edge of the module towards dark neighbours to join them
+                let extent = |joined: bool| if joined { 0.5 } else { half };
+                let (x0, x1) = (x - extent(left), x + extent(right));
+                let (y0, y1) = (y - extent(top), y + extent(bottom));
+                let corner = |a: bool, b: bool| if a || b { 0.0 } else { half };
+
+                RoundedRect {
+                    x: x0,
+                    y: y0,
+                    width: x1 - x0,
+                    height: y1 - y0,
+                    radii: [
+                        corner(top, left),
+                        corner(top, right),
+                        corner(bottom, right),
+                        corner(bottom, left),
+                    ],
+                }
+            }
+        }
+    }
+
+    /// Builds the shapes of a finder pattern
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - Row index of the top left module of the pattern
+    /// * `column` - Column index of the top left module of the pattern
+    ///
+    /// # Returns
+    ///
+    /// The outside and the hole of the outer ring and the centre, in modules from the top left
+    /// of the symbol
+    pub(crate) fn finder(&self, row: usize, column: usize) -> [RoundedRect; 3] {
+        let (x, y) = (column as f64 + 3.5, row as f64 + 3.5);
+        let outer_radius = self.outer_eye.corner_radius(3.5);
+
+        [
+            RoundedRect::centred(x, y, 3.5, outer_radius),
+            // The ring keeps a width of one module around the corners
+            RoundedRect::centred(x, y, 2.5, (outer_radius - 1.0).max(0.0)),
+            RoundedRect::centred(x, y, 1.5, self.inner_eye.corner_radius(1.5)),
+        ]
+    }
+}
+
+impl BitMap {
+    /// Returns the row and column of the top left module of the finder pattern containing a
+    /// module
+    pub(crate) fn finder_origin(&self, row: usize, column: usize) -> (usize, usize) {
+        let far = self.size() - 7;
+
+        (
+            if row >= far { far } else { 0 },
+            if column >= far { far } else { 0 },
+        )
+    }
+
+    /// Checks whether a point of the symbol is dark when drawn with a style
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Horizontal position in modules from the left of the symbol
+    /// * `y` - Vertical position in modules from the top of the symbol
+    /// * `style` - Module and finder pattern shapes
+    pub(crate) fn is_dark_styled(&self, x: f64, y: f64, style: &ModuleStyle) -> bool {
+        if x < 0.0 || y < 0.0 {
+            return false;
+        }
+
+        let (row, column) = (y as usize, x as usize);
+        if row >= self.size() || column >= self.size() {
+            return false;
+        }
+
+        match self.role(row, column) {
+            ModuleRole::FinderOuter | ModuleRole::FinderInner => {
+                let (origin_row, origin_column) = self.finder_origin(row, column);
+                let [outside, hole, centre] = style.finder(origin_row, origin_column);
+
+                (outside.contains(x, y) && !hole.contains(x, y)) || centre.contains(x, y)
+            }
+            ModuleRole::Data => {
+                self.is_dark(row, column, 0) && style.data_module(self, row, column).contains(x, y)
+            }
+            _ => self.is_dark(row, column, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounded_rect() {
+        let circle = RoundedRect::centred(0.5, 0.5, 0.5, 0.5);
+        assert!(circle.contains(0.5, 0.5));
+        assert!(circle.contains(0.5, 0.01));
+        assert!(!circle.contains(0.05, 0.05));
+        assert_eq!(
+            circle.svg_path(),
+            "M0.5,0h0a0.5,0.5 0 0 1 0.5,0.5v0a0.5,0.5 0 0 1 -0.5,0.5h0a0.5,0.5 0 0 1 -0.5,-0.5v0a0.5,0.5 0 0 1 0.5,-0.5z"
+        );
+
+        let square = RoundedRect::centred(1.5, 1.5, 0.5, 0.0);
+        assert!(square.contains(1.01, 1.99));
+        assert_eq!(square.svg_path(), "M1,1h1v1h-1v-1z");
+    }
+
+    #[test]
+    fn test_connected_modules() {
+        let mut bit_map = BitMap::new(3);
+        bit_map.set(1, 0, 1);
+        bit_map.set(1, 1, 1);
+
+        let style = ModuleStyle {
+            shape: ModuleShape::Connected,
+            fill_ratio: 0.5,
+            ..ModuleStyle::default()
+        };
+
+        // Joined on the right, rounded on the left
+        let module = style.data_module(&bit_map, 1, 0);
+        assert_eq!((module.x, module.width), (0.25, 0.75));
+        assert_eq!(module.radii, [0.25, 0.0, 0.0, 0.25]);
+
+        assert!(bit_map.is_dark_styled(1.0, 1.5, &style));
+        assert!(!bit_map.is_dark_styled(0.27, 1.27, &style));
+        assert!(!bit_map.is_dark_styled(1.5, 0.5, &style));
+    }
+}
@@ -0,0 +1,443 @@
+//! # SVG
+//!
+//! A vector writer for printing QR codes at any size. The dark modules are drawn as a single
+//! `<path>` made of the merged horizontal runs of every row, in a `viewBox` measured in modules.
+//! Styled codes draw every data module as its own shape and the finder patterns as one
+//! even-odd filled path each. With region colours every region gets its own path, filled with a
+//! colour or a gradient from the `<defs>`.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! qr_code.save_svg("qr_code.svg", &SvgOptions::default())?;
+//! ```
+
+use super::paint::{ContrastWarning, Paint, Region, RegionColors};
+use super::style::ModuleStyle;
+use super::style::RoundedRect;
+use super::{format_number, Color};
+use crate::bit_utils::bitmap::{BitMap, ModuleRole};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Options for writing SVG images
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SvgOptions {
+    /// Size of a module in user units, used for the `width` and `height` of the image
+    pub scale: usize,
+    /// Width of the quiet zone around the symbol in modules
+    pub quiet_zone: usize,
+    /// Colour of the dark modules
+    pub dark: Color,
+    /// Colour of the light modules and the quiet zone, `None` leaves them transparent
+    pub light: Option<Color>,
+    /// Shapes of the modules and finder patterns
+    pub style: ModuleStyle,
+    /// Colours and gradients of the regions of the symbol, taking the place of the dark colour
+    pub colors: RegionColors,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            scale: 8,
+            quiet_zone: 4,
+            dark: Color::BLACK,
+            light: Some(Color::WHITE),
+            style: ModuleStyle::default(),
+            colors: RegionColors::default(),
+        }
+    }
+}
+
+impl SvgOptions {
+    /// Checks the colours of the dark modules against the light colour
+    ///
+    /// # Returns
+    ///
+    /// A warning for every colour scanners may not tell apart from the light colour, which is
+    /// taken as white when transparent
+    pub fn contrast_warnings(&self) -> Vec<ContrastWarning> {
+        self.colors
+            .contrast_warnings(self.dark, self.light.unwrap_or(Color::WHITE))
+    }
+}
+
+/// Builds the gradient element of a paint, laid out over the symbol
+///
+/// # Arguments
+///
+/// * `paint` - The paint of a region
+/// * `id` - Identifier to refer to the gradient by
+/// * `quiet_zone` - Width of the quiet zone in modules
+/// * `size` - Width of the symbol in modules
+///
+/// # Returns
+///
+/// The gradient element, or `None` for solid colours
+fn svg_gradient(paint: &Paint, id: &str, quiet_zone: usize, size: usize) -> Option<String> {
+    let n = format_number;
+    let position = |fraction: f64| n(quiet_zone as f64 + fraction * size as f64);
+    let stops = |first: Color, last: Color| {
+        format!(
+            r#"<stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/>"#,
+            first, last
+        )
+    };
+
+    match *paint {
+        Paint::Solid(_) => None,
+        Paint::Linear {
+            start,
+            end,
+            from,
+            to,
+        } => Some(format!(
+            r#"<linearGradient id="{}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}">{}</linearGradient>"#,
+            id,
+            position(from.0),
+            position(from.1),
+            position(to.0),
+            position(to.1),
+            stops(start, end)
+        )),
+        Paint::Radial { centre, edge } => Some(format!(
+            r#"<radialGradient id="{}" gradientUnits="userSpaceOnUse" cx="{}" cy="{}" r="{}">{}</radialGradient>"#,
+            id,
+            position(0.5),
+            position(0.5),
+            n(size as f64 / 2.0),
+            stops(centre, edge)
+        )),
+    }
+}
+
+impl BitMap {
+    /// Builds the path data of the dark modules, one rectangle per horizontal run
+    ///
+    /// # Arguments
+    ///
+    /// * `quiet_zone` - Width of the quiet zone in modules
+    /// * `region` - Region of the modules to include, `None` for all of them
+    fn svg_path_data(&self, quiet_zone: usize, region: Option<Region>) -> String {
+        let mut path = String::new();
+
+        for row in 0..self.size() {
+            let runs = match region {
+                Some(region) => self.region_dark_runs(row, region),
+                None => self.dark_runs(row),
+            };
+
+            for (column, length) in runs {
+                path.push_str(&format!(
+                    "M{},{}h{}v1h-{}z",
+                    column + quiet_zone,
+                    row + quiet_zone,
+                    length,
+                    length
+                ));
+            }
+        }
+
+        path
+    }
+
+    /// Builds the path data of styled modules, with one shape per data module
+    ///
+    /// # Arguments
+    ///
+    /// * `quiet_zone` - Width of the quiet zone in modules
+    /// * `style` - Module and finder pattern shapes
+    /// * `region` - Region of the modules to include, `None` for all of them
+    ///
+    /// # Returns
+    ///
+    /// The path data of the modules and of the finder patterns, which are filled even-odd to
+    /// leave the hole of the outer ring
+    fn svg_styled_path_data(
+        &self,
+        quiet_zone: usize,
+        style: &ModuleStyle,
+        region: Option<Region>,
+    ) -> (String, String) {
+        let included = |role: ModuleRole| region.is_none_or(|region| Region::of(role) == region);
+        let offset = quiet_zone as f64;
+        let shift = |rect: RoundedRect| RoundedRect {
+            x: rect.x + offset,
+            y: rect.y + offset,
+            ..rect
+        };
+
+        let mut modules = String::new();
+
+        for row in 0..self.size() {
+            for column in 0..self.size() {
+                if !self.is_dark(row, column, 0) || !included(self.role(row, column)) {
+                    continue;
+                }
+
+                match self.role(row, column) {
+                    ModuleRole::FinderOuter | ModuleRole::FinderInner => {}
+                    ModuleRole::Data => {
+                        let shape = style.data_module(self, row, column);
+                        modules.push_str(&shift(shape).svg_path());
+                    }
+                    _ => modules.push_str(&format!(
+                        "M{},{}h1v1h-1z",
+                        column + quiet_zone,
+                        row + quiet_zone
+                    )),
+                }
+            }
+        }
+
+        let far = self.size().saturating_sub(7);
+        let mut finders = String::new();
+
+        for (row, column) in [(0, 0), (0, far), (far, 0)] {
+            if matches!(
+                self.role(row, column),
+                ModuleRole::FinderOuter | ModuleRole::FinderInner
+            ) && included(ModuleRole::FinderOuter)
+            {
+                for shape in style.finder(row, column) {
+                    finders.push_str(&shift(shape).svg_path());
+                }
+            }
+        }
+
+        (modules, finders)
+    }
+
+    /// Writes the bitmap as an SVG image
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Scale, quiet zone and colours of the image
+    pub fn write_svg<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &SvgOptions,
+    ) -> io::Result<()> {
+        self.write_svg_elements(writer, options, None)?;
+        writeln!(writer, "</svg>")
+    }
+
+    /// Writes the SVG image up to the closing tag, so more elements can be drawn on top
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the image data
+    /// * `options` - Scale, quiet zone and colours of the image
+    /// * `view_box` - Left, top, width and height of the image in modules, the symbol with its
+    ///   quiet zone when `None`
+    pub(crate) fn write_svg_elements<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &SvgOptions,
+        view_box: Option<(f64, f64, f64, f64)>,
+    ) -> io::Result<()> {
+        let modules = (self.size() + 2 * options.quiet_zone) as f64;
+        let (left, top, width, height) = view_box.unwrap_or((0.0, 0.0, modules, modules));
+        let n = format_number;
+
+        let styled = options.style != ModuleStyle::default();
+
+        // Curved shapes need anti-aliasing
+        let rendering = if styled {
+            "geometricPrecision"
+        } else {
+            "crispEdges"
+        };
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{}" height="{}" viewBox="{} {} {} {}" shape-rendering="{}">"#,
+            n(width * options.scale as f64),
+            n(height * options.scale as f64),
+            n(left),
+            n(top),
+            n(width),
+            n(height),
+            rendering
+        )?;
+
+        if let Some(light) = options.light {
+            // The image only starts away from the origin when it has grown to the left or top
+            let origin = match view_box {
+                Some(_) => format!(r#"x="{}" y="{}" "#, n(left), n(top)),
+                None => String::new(),
+            };
+
+            writeln!(
+                writer,
+                r#"<rect {}width="{}" height="{}" fill="{}"/>"#,
+                origin,
+                n(width),
+                n(height),
+                light
+            )?;
+        }
+
+        // Without region colours every dark module is drawn with the dark colour at once
+        let regions: Vec<(Option<Region>, String)> = if options.colors == RegionColors::default() {
+            vec![(None, options.dark.to_string())]
+        } else {
+            let mut gradients = String::new();
+            let mut regions = Vec::new();
+
+            for region in Region::ALL {
+                let paint = options.colors.paint(region, options.dark);
+                let id = format!("{}-gradient", region.name());
+
+                match svg_gradient(&paint, &id, options.quiet_zone, self.size()) {
+                    Some(gradient) => {
+                        gradients.push_str(&gradient);
+                        regions.push((Some(region), format!("url(#{})", id)));
+                    }
+                    None => regions.push((Some(region), paint.colors()[0].to_string())),
+                }
+            }
+
+            if !gradients.is_empty() {
+                writeln!(writer, "<defs>{}</defs>", gradients)?;
+            }
+
+            regions
+        };
+
+        for (region, fill) in regions {
+            if styled {
+                let (modules, finders) =
+                    self.svg_styled_path_data(options.quiet_zone, &options.style, region);
+
+                if !modules.is_empty() {
+                    writeln!(writer, r#"<path fill="{}" d="{}"/>"#, fill, modules)?;
+                }
+
+                if !finders.is_empty() {
+                    writeln!(
+                        writer,
+                        r#"<path fill="{}" fill-rule="evenodd" d="{}"/>"#,
+                        fill, finders
+                    )?;
+                }
+            } else {
+                let modules = self.svg_path_data(options.quiet_zone, region);
+
+                if region.is_none() || !modules.is_empty() {
+                    writeln!(writer, r#"<path fill="{}" d="{}"/>"#, fill, modules)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves the bitmap to a file in SVG format
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the image
+    /// * `options` - Scale, quiet zone and colours of the image
+    pub fn save_svg<P>(&self, path: P, options: &SvgOptions) -> io::Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_svg(&mut writer, options)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::style::{EyeShape, ModuleShape};
+
+    #[test]
+    fn test_write_svg() {
+        let mut bit_map = BitMap::new(3);
+        bit_map.set(0, 0, 1);
+        bit_map.set(0, 1, 1);
+        bit_map.set(2, 2, 1);
+
+        assert_eq!(bit_map.svg_path_data(1, None), "M1,1h2v1h-2zM3,3h1v1h-1z");
+
+        let options = SvgOptions {
+            scale: 10,
+            quiet_zone: 1,
+            dark: Color::rgb(0x12, 0x34, 0x56),
+            light: None,
+            ..SvgOptions::default()
+        };
+
+        let mut svg: Vec<u8> = Vec::new();
+        bit_map.write_svg(&mut svg, &options).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.contains(r#"width="50" height="50" viewBox="0 0 5 5""#));
+        assert!(svg.contains(r#"shape-rendering="crispEdges""#));
+        assert!(svg.contains(r##"<path fill="#123456" d="M1,1h2v1h-2zM3,3h1v1h-1z"/>"##));
+        assert!(!svg.contains("<rect"));
+    }
+
+    #[test]
+    fn test_write_styled_svg() {
+        let qr_code = crate::create_qr_code("HELLO", crate::ErrorCorrectionLevel::L);
+        let options = SvgOptions {
+            style: ModuleStyle {
+                shape: ModuleShape::Circle,
+                outer_eye: EyeShape::Circle,
+                ..ModuleStyle::default()
+            },
+            ..SvgOptions::default()
+        };
+
+        let (modules, finders) = qr_code.svg_styled_path_data(4, &options.style, None);
+
+        // The timing patterns stay square and the finder patterns are three sets of circles
+        assert!(modules.contains("M12,10h1v1h-1z"));
+        assert_eq!(finders.matches('M').count(), 9);
+        assert!(finders.starts_with("M7.5,4h0a3.5,3.5 0 0 1 3.5,3.5"));
+
+        let mut svg: Vec<u8> = Vec::new();
+        qr_code.write_svg(&mut svg, &options).unwrap();
+        assert!(String::from_utf8(svg)
+            .unwrap()
+            .contains(r#"fill-rule="evenodd""#));
+    }
+
+    #[test]
+    fn test_write_region_colors() {
+        let qr_code = crate::create_qr_code("HELLO", crate::ErrorCorrectionLevel::L);
+        let options = SvgOptions {
+            colors: RegionColors {
+                finder: Some(Paint::Solid(Color::rgb(255, 0, 0))),
+                data: Some(Paint::Linear {
+                    start: Color::BLACK,
+                    end: Color::rgb(0, 0, 128),
+                    from: (0.0, 0.0),
+                    to: (1.0, 1.0),
+                }),
+                ..RegionColors::default()
+            },
+            ..SvgOptions::default()
+        };
+
+        let mut svg: Vec<u8> = Vec::new();
+        qr_code.write_svg(&mut svg, &options).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.contains(
+            r#"<defs><linearGradient id="data-gradient" gradientUnits="userSpaceOnUse" x1="4" y1="4" x2="25" y2="25">"#
+        ));
+        assert!(svg.contains(r#"<path fill="url(#data-gradient)" d="M"#));
+        assert!(svg.contains(r##"<path fill="#ff0000" d="M4,4h7v1h-7z"##));
+
+        // Version 1 has no alignment patterns, only the timing patterns are left in black
+        assert_eq!(svg.matches("<path").count(), 3);
+        assert!(svg.contains(r##"<path fill="#000000" d="M12,10h1v1h-1z"##));
+    }
+}
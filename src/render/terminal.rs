@@ -0,0 +1,226 @@
+//! # Terminal Images
+//!
+//! Inline image output for terminals with a graphics protocol. The Kitty graphics protocol and
+//! iTerm2's OSC 1337 both take a base64 encoded PNG image, Sixel terminals take a Sixel image
+//! and every other terminal gets ANSI coloured half blocks.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! print!("{}", qr_code.to_terminal(TerminalProtocol::detect()));
+//! ```
+
+use super::ansi::AnsiOptions;
+use super::{base64_encode, RenderOptions};
+use crate::bit_utils::bitmap::BitMap;
+use std::env;
+use std::io::{self, Write};
+
+// Largest base64 payload of a single Kitty graphics escape sequence
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Ways of showing an image in a terminal
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TerminalProtocol {
+    /// Kitty graphics protocol
+    Kitty,
+    /// iTerm2 inline images (OSC 1337)
+    Iterm2,
+    /// Sixel graphics
+    Sixel,
+    /// ANSI coloured text blocks, supported by every terminal
+    Text,
+}
+
+impl TerminalProtocol {
+    /// Picks the best protocol for the terminal from the environment variables
+    ///
+    /// # Arguments
+    ///
+    /// * `term` - Value of `TERM`
+    /// * `term_program` - Value of `TERM_PROGRAM`
+    /// * `kitty_window_id` - Value of `KITTY_WINDOW_ID`
+    pub fn from_env_vars(
+        term: Option<&str>,
+        term_program: Option<&str>,
+        kitty_window_id: Option<&str>,
+    ) -> Self {
+        let term = term.unwrap_or_default();
+
+        if kitty_window_id.is_some() || term.contains("kitty") || term.contains("ghostty") {
+            return TerminalProtocol::Kitty;
+        }
+
+        match term_program {
+            Some("iTerm.app" | "WezTerm" | "mintty") => TerminalProtocol::Iterm2,
+            _ if term.contains("sixel") || term.contains("mlterm") || term.starts_with("foot") => {
+                TerminalProtocol::Sixel
+            }
+            _ => TerminalProtocol::Text,
+        }
+    }
+
+    /// Picks the best protocol for the current terminal from `TERM`, `TERM_PROGRAM` and
+    /// `KITTY_WINDOW_ID`
+    pub fn detect() -> Self {
+        let term = env::var("TERM").ok();
+        let term_program = env::var("TERM_PROGRAM").ok();
+        let kitty_window_id = env::var("KITTY_WINDOW_ID").ok();
+
+        Self::from_env_vars(
+            term.as_deref(),
+            term_program.as_deref(),
+            kitty_window_id.as_deref(),
+        )
+    }
+}
+
+impl BitMap {
+    /// Encodes the bitmap as a PNG image in memory
+    fn png_bytes(&self, options: &RenderOptions) -> Vec<u8> {
+        let mut png: Vec<u8> = Vec::new();
+        // Writing to memory cannot fail
+        _ = self.write_png(&mut png, options);
+
+        png
+    }
+
+    /// Encodes the bitmap for the Kitty graphics protocol
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the PNG image
+    ///
+    /// # Returns
+    ///
+    /// The escape sequences transmitting and displaying the image
+    pub fn to_kitty(&self, options: &RenderOptions) -> String {
+        let payload = base64_encode(&self.png_bytes(options));
+        let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+        let mut output = String::with_capacity(payload.len() + 32 * chunks.len());
+
+        for (index, chunk) in chunks.iter().enumerate() {
+            let more = (index + 1 < chunks.len()) as u8;
+
+            // Only the first chunk carries the format (PNG) and action (transmit and display)
+            if index == 0 {
+                output.push_str(&format!("\x1b_Gf=100,a=T,m={};", more));
+            } else {
+                output.push_str(&format!("\x1b_Gm={};", more));
+            }
+
+            output.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+            output.push_str("\x1b\\");
+        }
+
+        output
+    }
+
+    /// Encodes the bitmap as an iTerm2 inline image
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Module size, quiet zone and colours of the PNG image
+    ///
+    /// # Returns
+    ///
+    /// The OSC 1337 escape sequence displaying the image
+    pub fn to_iterm2(&self, options: &RenderOptions) -> String {
+        let png = self.png_bytes(options);
+
+        format!(
+            "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+            png.len(),
+            base64_encode(&png)
+        )
+    }
+
+    /// Renders the bitmap for a terminal
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol` - The image protocol supported by the terminal
+    ///
+    /// # Returns
+    ///
+    /// The escape sequences or text showing the QR code, followed by a newline
+    pub fn to_terminal(&self, protocol: TerminalProtocol) -> String {
+        match protocol {
+            TerminalProtocol::Kitty => self.to_kitty(&RenderOptions::default()) + "\n",
+            TerminalProtocol::Iterm2 => self.to_iterm2(&RenderOptions::default()) + "\n",
+            TerminalProtocol::Sixel => self.to_sixel(&RenderOptions::default()) + "\n",
+            TerminalProtocol::Text => self.render_ansi(&AnsiOptions {
+                half_block: true,
+                ..AnsiOptions::default()
+            }),
+        }
+    }
+
+    /// Writes the bitmap for a terminal
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the output, usually standard output
+    /// * `protocol` - The image protocol supported by the terminal
+    pub fn write_terminal<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        protocol: TerminalProtocol,
+    ) -> io::Result<()> {
+        writer.write_all(self.to_terminal(protocol).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_images() {
+        assert_eq!(base64_encode(b"QR"), "UVI=");
+        assert_eq!(base64_encode(b"DEWQ!"), "REVXUSE=");
+
+        let bit_map = BitMap::new(21);
+        let options = RenderOptions::default();
+        let payload = base64_encode(&bit_map.png_bytes(&options));
+
+        let kitty = bit_map.to_kitty(&options);
+        assert!(kitty.starts_with("\x1b_Gf=100,a=T,m=0;iVBORw0KGgo"));
+        assert!(kitty.ends_with("\x1b\\"));
+        assert!(kitty.contains(&payload));
+
+        let mut output: Vec<u8> = Vec::new();
+        bit_map
+            .write_terminal(&mut output, TerminalProtocol::Kitty)
+            .unwrap();
+        assert_eq!(output, (kitty.clone() + "\n").into_bytes());
+
+        let iterm2 = bit_map.to_iterm2(&options);
+        assert!(iterm2.starts_with("\x1b]1337;File=inline=1;size="));
+        assert!(iterm2.ends_with(&format!(":{}\x07", payload)));
+    }
+
+    #[test]
+    fn test_protocol_detection() {
+        let detect = TerminalProtocol::from_env_vars;
+
+        assert_eq!(
+            detect(Some("xterm-256color"), None, Some("1")),
+            TerminalProtocol::Kitty
+        );
+        assert_eq!(
+            detect(Some("xterm-kitty"), None, None),
+            TerminalProtocol::Kitty
+        );
+        assert_eq!(
+            detect(Some("xterm-256color"), Some("iTerm.app"), None),
+            TerminalProtocol::Iterm2
+        );
+        assert_eq!(detect(Some("foot"), None, None), TerminalProtocol::Sixel);
+        assert_eq!(
+            detect(Some("xterm-256color"), Some("Apple_Terminal"), None),
+            TerminalProtocol::Text
+        );
+        assert_eq!(detect(None, None, None), TerminalProtocol::Text);
+    }
+}
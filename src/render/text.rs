@@ -0,0 +1,190 @@
+//! # Text
+//!
+//! Compact renderings of QR codes for terminals. Half blocks (`▀▄█`) fit two module rows in
+//! one line of text, so a module is one character wide and the output is half the width of the
+//! `Display` output. Braille characters fit 2 x 4 modules in a character, but the gaps between
+//! the dots make them harder to scan.
+//!
+//! ```rust,ignore
+//! let qr_code = create_qr_code("HELLO, WORLD!", ErrorCorrectionLevel::Q);
+//! print!("{}", qr_code.render_text(&TextOptions::default()));
+//! ```
+
+use super::{RenderOptions, Renderer};
+use crate::bit_utils::bitmap::BitMap;
+use std::io::{self, Write};
+
+/// Characters used to draw the modules
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TextMode {
+    /// One character per module column and two module rows per line
+    HalfBlock,
+    /// Braille patterns of 2 x 4 modules per character
+    Braille,
+}
+
+/// Which modules the characters draw, depending on the terminal colours
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Polarity {
+    /// Characters draw the dark modules, for dark text on a light background
+    DarkOnLight,
+    /// Characters draw the light modules, for light text on a dark background
+    LightOnDark,
+}
+
+/// Options for rendering text
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct TextOptions {
+    pub mode: TextMode,
+    pub polarity: Polarity,
+    /// Width of the quiet zone around the symbol in modules
+    pub quiet_zone: usize,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            mode: TextMode::HalfBlock,
+            polarity: Polarity::LightOnDark,
+            quiet_zone: 4,
+        }
+    }
+}
+
+// Bit of each dot in a braille pattern, indexed by row and column of the 2 x 4 cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+impl BitMap {
+    /// Renders the bitmap as lines of text
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Characters, polarity and quiet zone of the rendering
+    ///
+    /// # Returns
+    ///
+    /// The rendering with every line terminated by a newline
+    pub fn render_text(&self, options: &TextOptions) -> String {
+        let modules = self.size() + 2 * options.quiet_zone;
+
+        // Whether the module is drawn, positions past the quiet zone are never drawn
+        let ink = |row: usize, column: usize| {
+            row < modules
+                && column < modules
+                && self.is_dark(row, column, options.quiet_zone)
+                    == (options.polarity == Polarity::DarkOnLight)
+        };
+
+        let mut output = String::new();
+
+        match options.mode {
+            TextMode::HalfBlock => {
+                for row in (0..modules).step_by(2) {
+                    for column in 0..modules {
+                        output.push(match (ink(row, column), ink(row + 1, column)) {
+                            (true, true) => '█',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (false, false) => ' ',
+                        });
+                    }
+                    output.push('\n');
+                }
+            }
+            TextMode::Braille => {
+                for row in (0..modules).step_by(4) {
+                    for column in (0..modules).step_by(2) {
+                        let mut pattern = 0x2800;
+
+                        for (dy, dots) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, dot) in dots.iter().enumerate() {
+                                if ink(row + dy, column + dx) {
+                                    pattern |= dot;
+                                }
+                            }
+                        }
+
+                        output.push(char::from_u32(pattern).unwrap_or(' '));
+                    }
+                    output.push('\n');
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Writes the bitmap as lines of text
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the text
+    /// * `options` - Characters, polarity and quiet zone of the rendering
+    pub fn write_text<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &TextOptions,
+    ) -> io::Result<()> {
+        writer.write_all(self.render_text(options).as_bytes())
+    }
+}
+
+/// Writes text with these options, the quiet zone is taken from the render options and one
+/// character is drawn per module (or cell of modules) whatever the module size
+impl Renderer for TextOptions {
+    fn render(
+        &self,
+        bit_map: &BitMap,
+        writer: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let text = bit_map.render_text(&TextOptions {
+            quiet_zone: options.quiet_zone_modules,
+            ..*self
+        });
+
+        writer.write_all(text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_text() {
+        let mut bit_map = BitMap::new(2);
+        bit_map.set(0, 0, 1);
+        bit_map.set(1, 1, 1);
+
+        let mut options = TextOptions {
+            mode: TextMode::HalfBlock,
+            polarity: Polarity::DarkOnLight,
+            quiet_zone: 1,
+        };
+        assert_eq!(bit_map.render_text(&options), " ▄  \n  ▀ \n");
+
+        options.polarity = Polarity::LightOnDark;
+        assert_eq!(bit_map.render_text(&options), "█▀██\n██▄█\n");
+
+        options.mode = TextMode::Braille;
+        options.polarity = Polarity::DarkOnLight;
+        assert_eq!(bit_map.render_text(&options), "⠐⠄\n");
+
+        // Rendering through the trait takes the quiet zone from the render options
+        let render_options = RenderOptions {
+            quiet_zone_modules: 1,
+            ..RenderOptions::default()
+        };
+        let mut text: Vec<u8> = Vec::new();
+        bit_map
+            .render(&TextOptions::default(), &mut text, &render_options)
+            .unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "█▀██\n██▄█\n");
+
+        // The default quiet zone is 4 modules on every side
+        let text = bit_map.render_text(&TextOptions::default());
+        assert_eq!(text.lines().count(), 5);
+        assert!(text.lines().all(|line| line.chars().count() == 10));
+    }
+}